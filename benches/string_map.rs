@@ -1,3 +1,4 @@
+use containers::CompactStringMap;
 use containers::StringMap;
 use criterion::criterion_group;
 use criterion::criterion_main;
@@ -34,6 +35,77 @@ pub fn string_map_insertion<const N: usize>(c: &mut Criterion) {
     });
 }
 
+pub fn string_map_bulk_insertion<const N: usize>(c: &mut Criterion) {
+    let mut name = "string map bulk insertion ".to_string();
+    name.push_str(&N.to_string());
+    c.bench_function(name.as_str(), |b| {
+        b.iter(|| {
+            black_box(
+                (0..N)
+                    .map(|i| (black_box(i.to_string()), black_box(i)))
+                    .collect::<StringMap<usize>>(),
+            );
+        })
+    });
+}
+
+pub fn string_map_extend<const N: usize>(c: &mut Criterion) {
+    let mut name = "string map extend ".to_string();
+    name.push_str(&N.to_string());
+
+    c.bench_function(name.as_str(), |b| {
+        b.iter_custom(|iters| {
+            let mut ret: Duration = Default::default();
+            for _ in 0..iters {
+                let mut map = (0..N)
+                    .step_by(2)
+                    .map(|i| (i.to_string(), i))
+                    .collect::<StringMap<usize>>();
+                let batch = (1..N)
+                    .step_by(2)
+                    .map(|i| (i.to_string(), i))
+                    .collect::<Vec<_>>();
+                let start = Instant::now();
+                map.extend(black_box(batch));
+                ret += start.elapsed();
+            }
+            ret
+        })
+    });
+}
+
+pub fn string_map_update<const N: usize>(c: &mut Criterion) {
+    let mut name = "string map update ".to_string();
+    name.push_str(&N.to_string());
+
+    let mut map = (0..N).map(|i| (i.to_string(), i)).collect::<StringMap<_>>();
+    let keys = (0..N).map(|i| i.to_string()).collect::<Vec<_>>();
+
+    c.bench_function(name.as_str(), |b| {
+        b.iter(|| {
+            for (i, key) in keys.iter().enumerate() {
+                black_box(map.insert(black_box(key.clone()), i));
+            }
+        })
+    });
+}
+
+pub fn string_map_update_ref<const N: usize>(c: &mut Criterion) {
+    let mut name = "string map update ref ".to_string();
+    name.push_str(&N.to_string());
+
+    let mut map = (0..N).map(|i| (i.to_string(), i)).collect::<StringMap<_>>();
+    let keys = (0..N).map(|i| i.to_string()).collect::<Vec<_>>();
+
+    c.bench_function(name.as_str(), |b| {
+        b.iter(|| {
+            for (i, key) in keys.iter().enumerate() {
+                black_box(map.insert_ref(black_box(key), i));
+            }
+        })
+    });
+}
+
 pub fn hash_map_access<const N: usize>(c: &mut Criterion) {
     let mut name = "string hash map access ".to_string();
     name.push_str(&N.to_string());
@@ -68,6 +140,53 @@ pub fn string_map_access<const N: usize>(c: &mut Criterion) {
     });
 }
 
+pub fn hash_indexed_string_map_access<const N: usize>(c: &mut Criterion) {
+    let mut name = "hash indexed string map access ".to_string();
+    name.push_str(&N.to_string());
+
+    let mut map = StringMap::with_hash_index();
+    for i in 0..N {
+        map.insert(i.to_string(), i);
+    }
+    let mid = (N >> 1).to_string();
+
+    c.bench_function(name.as_str(), |b| {
+        b.iter(|| {
+            black_box(map[&mid]);
+        })
+    });
+}
+
+pub fn compact_string_map_insertion<const N: usize>(c: &mut Criterion) {
+    let mut name = "compact string map insertion ".to_string();
+    name.push_str(&N.to_string());
+    c.bench_function(name.as_str(), |b| {
+        b.iter(|| {
+            let mut v = CompactStringMap::<usize>::new();
+            for i in 0..N {
+                v.insert(black_box(&i.to_string()), black_box(i));
+            }
+        })
+    });
+}
+
+pub fn compact_string_map_access<const N: usize>(c: &mut Criterion) {
+    let mut name = "compact string map access ".to_string();
+    name.push_str(&N.to_string());
+
+    let mut map = CompactStringMap::new();
+    for i in 0..N {
+        map.insert(&i.to_string(), i);
+    }
+    let mid = (N >> 1).to_string();
+
+    c.bench_function(name.as_str(), |b| {
+        b.iter(|| {
+            black_box(map[&mid]);
+        })
+    });
+}
+
 pub fn hash_map_presence<const N: usize>(c: &mut Criterion) {
     let mut name = "string hash map presence ".to_string();
     name.push_str(&N.to_string());
@@ -102,6 +221,23 @@ pub fn string_map_presence<const N: usize>(c: &mut Criterion) {
     });
 }
 
+pub fn hash_indexed_string_map_presence<const N: usize>(c: &mut Criterion) {
+    let mut name = "hash indexed string map presence ".to_string();
+    name.push_str(&N.to_string());
+
+    let mut map = StringMap::with_hash_index();
+    for i in 0..N {
+        map.insert(i.to_string(), i);
+    }
+    let mid = (N >> 1).to_string();
+
+    c.bench_function(name.as_str(), |b| {
+        b.iter(|| {
+            black_box(map.contains_key(&mid));
+        })
+    });
+}
+
 pub fn hash_map_removal<const N: usize>(c: &mut Criterion) {
     let mut name = "string hash map removal ".to_string();
     name.push_str(&N.to_string());
@@ -154,10 +290,18 @@ criterion_group!(
     string_map,
     hash_map_insertion<100_000>,
     string_map_insertion<100_000>,
+    string_map_bulk_insertion<100_000>,
+    string_map_extend<100_000>,
+    string_map_update<100_000>,
+    string_map_update_ref<100_000>,
     hash_map_access<100_000>,
     string_map_access<100_000>,
+    hash_indexed_string_map_access<100_000>,
+    compact_string_map_insertion<100_000>,
+    compact_string_map_access<100_000>,
     hash_map_presence<100_000>,
     string_map_presence<100_000>,
+    hash_indexed_string_map_presence<100_000>,
     hash_map_removal<100_000>,
     string_map_removal<100_000>,
 );
@@ -0,0 +1,269 @@
+use std::ops::Index;
+use std::ops::IndexMut;
+
+/// A [`StringMap`](crate::StringMap) whose keys all live in a single `String`.
+///
+/// Each key is a `(offset, len)` range into the arena, and the ranges are kept
+/// sorted by the key they point to. This saves one allocation per key and
+/// keeps the keys close together in memory. Removed keys leave dead bytes in
+/// the arena, which is rebuilt once they make up more than half of it.
+///
+/// Offsets are stored as `u32`, so the arena holds at most `u32::MAX` bytes of
+/// keys, dead bytes included.
+#[derive(Clone)]
+pub struct CompactStringMap<T> {
+    arena: String,
+    ranges: Vec<(u32, u32)>,
+    values: Vec<T>,
+    dead_bytes: usize,
+}
+
+impl<T> Default for CompactStringMap<T> {
+    fn default() -> Self {
+        Self {
+            arena: String::new(),
+            ranges: vec![],
+            values: vec![],
+            dead_bytes: 0,
+        }
+    }
+}
+
+impl<T> CompactStringMap<T> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a map able to hold `capacity` entries, whose keys add up to
+    /// `key_bytes` bytes, without reallocating.
+    #[inline]
+    pub fn with_capacity(capacity: usize, key_bytes: usize) -> Self {
+        Self {
+            arena: String::with_capacity(key_bytes),
+            ranges: Vec::with_capacity(capacity),
+            values: Vec::with_capacity(capacity),
+            dead_bytes: 0,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        debug_assert_eq!(self.ranges.len(), self.values.len());
+        self.ranges.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes every entry, keeping the allocations.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.arena.clear();
+        self.ranges.clear();
+        self.values.clear();
+        self.dead_bytes = 0;
+    }
+
+    #[inline]
+    fn key(&self, range: (u32, u32)) -> &str {
+        let start = range.0 as usize;
+        &self.arena[start..start + range.1 as usize]
+    }
+
+    #[inline]
+    fn key_idx(&self, key: &str) -> Result<usize, usize> {
+        self.ranges.binary_search_by(|&r| self.key(r).cmp(key))
+    }
+
+    #[inline]
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.key_idx(key).is_ok()
+    }
+
+    #[inline]
+    pub fn get(&self, key: &str) -> Option<&T> {
+        self.key_idx(key).ok().map(|n| &self.values[n])
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut T> {
+        self.key_idx(key).ok().map(|n| &mut self.values[n])
+    }
+
+    /// Inserts `value` at `key`, returning the previous value if any. The key
+    /// is only copied into the arena when it is not present yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if copying the key would grow the arena, including the dead
+    /// bytes of removed keys, beyond `u32::MAX` bytes.
+    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
+        match self.key_idx(key) {
+            Ok(n) => Some(std::mem::replace(&mut self.values[n], value)),
+            Err(n) => {
+                // Checking the end is enough for the whole range to fit.
+                let end = to_u32(self.arena.len() + key.len());
+                let range = (end - key.len() as u32, key.len() as u32);
+                self.arena.push_str(key);
+                self.ranges.insert(n, range);
+                self.values.insert(n, value);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+        let n = self.key_idx(key).ok()?;
+        let (_, len) = self.ranges.remove(n);
+        let value = self.values.remove(n);
+
+        self.dead_bytes += len as usize;
+        if self.dead_bytes > self.arena.len() / 2 {
+            self.compact();
+        }
+        Some(value)
+    }
+
+    /// Rebuilds the arena with only the live keys, in key order.
+    fn compact(&mut self) {
+        let mut arena = String::with_capacity(self.arena.len() - self.dead_bytes);
+        for range in self.ranges.iter_mut() {
+            let start = range.0 as usize;
+            range.0 = to_u32(arena.len());
+            arena.push_str(&self.arena[start..start + range.1 as usize]);
+        }
+        self.arena = arena;
+        self.dead_bytes = 0;
+    }
+
+    #[inline]
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.ranges.iter().map(|&r| self.key(r))
+    }
+
+    #[inline]
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    #[inline]
+    pub fn values_mut(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+
+    #[inline]
+    pub fn items(&self) -> impl Iterator<Item = (&str, &T)> {
+        self.keys().zip(self.values.iter())
+    }
+
+    #[inline]
+    pub fn items_mut(&mut self) -> impl Iterator<Item = (&str, &mut T)> {
+        let arena = self.arena.as_str();
+        self.ranges
+            .iter()
+            .map(move |&(start, len)| &arena[start as usize..(start + len) as usize])
+            .zip(self.values.iter_mut())
+    }
+}
+
+#[inline]
+fn to_u32(n: usize) -> u32 {
+    u32::try_from(n).expect("CompactStringMap keys cannot exceed u32::MAX bytes in total")
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for CompactStringMap<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.items()).finish()
+    }
+}
+
+impl<T> Index<&str> for CompactStringMap<T> {
+    type Output = T;
+
+    fn index(&self, key: &str) -> &T {
+        self.get(key).unwrap()
+    }
+}
+
+impl<T> IndexMut<&str> for CompactStringMap<T> {
+    fn index_mut(&mut self, key: &str) -> &mut T {
+        self.get_mut(key).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CompactStringMap;
+
+    #[test]
+    fn insert_get_remove() {
+        let mut map = CompactStringMap::<u32>::new();
+        assert_eq!(map.insert("test", 10), None);
+        assert_eq!(map.insert("atest", 30), None);
+        assert_eq!(map.insert("test2", 20), None);
+        assert_eq!(map.insert("test", 5), Some(10));
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.arena, "testatesttest2");
+        assert_eq!(
+            map.keys().collect::<Vec<_>>(),
+            vec!["atest", "test", "test2"]
+        );
+        assert_eq!(map.values(), &[30, 5, 20]);
+
+        assert!(map.contains_key("test"));
+        assert!(!map.contains_key("tes"));
+        assert_eq!(map.get("test2"), Some(&20));
+        assert_eq!(map.get("test "), None);
+
+        map["atest"] += 1;
+        *map.get_mut("test2").unwrap() += 1;
+        for (_, value) in map.items_mut() {
+            *value *= 10;
+        }
+        assert_eq!(
+            map.items().collect::<Vec<_>>(),
+            vec![("atest", &310), ("test", &50), ("test2", &210)]
+        );
+
+        assert_eq!(map.remove("test"), Some(50));
+        assert_eq!(map.remove("test"), None);
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec!["atest", "test2"]);
+        assert_eq!(format!("{map:?}"), r#"{"atest": 310, "test2": 210}"#);
+    }
+
+    #[test]
+    fn compaction() {
+        let mut map = CompactStringMap::<usize>::new();
+        for i in 0..100 {
+            map.insert(&format!("{i:03}"), i);
+        }
+        assert_eq!(map.arena.len(), 300);
+
+        for i in 0..50 {
+            assert_eq!(map.remove(&format!("{i:03}")), Some(i));
+        }
+        assert_eq!(map.arena.len(), 300);
+        assert_eq!(map.dead_bytes, 150);
+
+        assert_eq!(map.remove("050"), Some(50));
+        assert_eq!(map.dead_bytes, 0);
+        assert_eq!(map.arena.len(), 147);
+        for i in 51..100 {
+            assert_eq!(map[&format!("{i:03}")], i);
+        }
+        assert_eq!(
+            map.keys().collect::<Vec<_>>(),
+            (51..100).map(|i| format!("{i:03}")).collect::<Vec<_>>()
+        );
+
+        map.insert("000", 0);
+        assert_eq!(map.keys().next(), Some("000"));
+        map.clear();
+        assert!(map.is_empty());
+        assert!(map.arena.is_empty());
+    }
+}
@@ -1,5 +1,12 @@
 mod sparsevec;
 pub use sparsevec::SparseVec;
 
-mod string_map;
+pub mod sorted_vec_map;
+pub use sorted_vec_map::EitherOrBoth;
+pub use sorted_vec_map::SortedVecMap;
+
+pub mod string_map;
 pub use string_map::StringMap;
+
+mod compact_string_map;
+pub use compact_string_map::CompactStringMap;
@@ -0,0 +1,1635 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ops::Bound;
+use std::ops::Index;
+use std::ops::IndexMut;
+use std::ops::RangeBounds;
+
+/// A map stored as two parallel vectors, the keys being kept sorted.
+///
+/// Lookups are binary searches, iteration is in key order, and insertion and
+/// removal shift the vectors. This is the machinery behind [`StringMap`](crate::StringMap).
+///
+/// The third parameter `Q` is the borrowed form of the keys: it is what keys
+/// are compared and hashed as, and what the iterators hand out. Lookups accept
+/// anything that borrows as `Q`, so `Q` must be the form callers will query
+/// with. For owned string-like keys this is `str`, which is what
+/// [`StringMap`](crate::StringMap) uses; for keys that are not borrowed as
+/// anything else, it is the key type itself.
+///
+/// ```
+/// use containers::SortedVecMap;
+/// use std::borrow::Cow;
+///
+/// // Keys are stored as `String` but compared, queried and yielded as `str`.
+/// let mut names = SortedVecMap::<String, u32, str>::new();
+/// names.insert("b".to_string(), 2);
+/// names.insert("a".to_string(), 1);
+/// assert_eq!(names.get("a"), Some(&1));
+/// assert_eq!(names.get(&"b".to_string()), Some(&2));
+/// assert_eq!(names.get(&Cow::Borrowed("c")), None);
+/// assert_eq!(names.keys_iter().collect::<Vec<&str>>(), vec!["a", "b"]);
+///
+/// // Keys that are not borrowed as anything else repeat their own type.
+/// let mut ids = SortedVecMap::<u64, &str, u64>::new();
+/// ids.insert(7, "seven");
+/// assert_eq!(ids.get(&7), Some(&"seven"));
+/// ```
+///
+/// Writing `SortedVecMap<String, u32, String>` instead would only allow
+/// lookups by `&String`, since `str` does not borrow as `String`.
+///
+/// Large maps can be built with [`SortedVecMap::with_hash_index`], which keeps
+/// the hashes of the keys on the side so that lookups of present keys skip
+/// most key comparisons.
+pub struct SortedVecMap<K, V, Q: ?Sized> {
+    keys: Vec<K>,
+    values: Vec<V>,
+    hash_index: Option<HashIndex<Q>>,
+    _borrowed: PhantomData<fn(&Q)>,
+}
+
+impl<K, V, Q: ?Sized> Default for SortedVecMap<K, V, Q> {
+    fn default() -> Self {
+        Self {
+            keys: vec![],
+            values: vec![],
+            hash_index: None,
+            _borrowed: PhantomData,
+        }
+    }
+}
+
+impl<K: Clone, V: Clone, Q: ?Sized> Clone for SortedVecMap<K, V, Q> {
+    fn clone(&self) -> Self {
+        Self {
+            keys: self.keys.clone(),
+            values: self.values.clone(),
+            hash_index: self.hash_index.clone(),
+            _borrowed: PhantomData,
+        }
+    }
+}
+
+/// Keys are sorted and unique, so equal maps have equal vectors. Whether a map
+/// has a hash index does not matter.
+impl<K: PartialEq, V: PartialEq, Q: ?Sized> PartialEq for SortedVecMap<K, V, Q> {
+    fn eq(&self, other: &Self) -> bool {
+        self.keys == other.keys && self.values == other.values
+    }
+}
+
+impl<K: Eq, V: Eq, Q: ?Sized> Eq for SortedVecMap<K, V, Q> {}
+
+impl<K, V, Q: ?Sized> SortedVecMap<K, V, Q> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            keys: Vec::with_capacity(capacity),
+            values: Vec::with_capacity(capacity),
+            hash_index: None,
+            _borrowed: PhantomData,
+        }
+    }
+
+    /// Creates a map that also keeps the hash of every key, as computed by
+    /// `hasher`, sorted in a side vector. Lookups search that vector first and
+    /// only compare the keys sharing the hash of the query, falling back to a
+    /// binary search over the keys when the query is absent.
+    ///
+    /// This costs 16 bytes per entry, and inserting or removing a key also
+    /// shifts the index.
+    #[inline]
+    pub fn with_hash_index_by(hasher: fn(&Q) -> u64) -> Self {
+        Self {
+            hash_index: Some(HashIndex::new(hasher)),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the keys as a slice, in order.
+    ///
+    /// This is a convenience tied to the current storage layout, and may be
+    /// deprecated: prefer [`SortedVecMap::keys_iter`].
+    #[inline]
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+
+    /// Returns the values as a slice, in key order.
+    ///
+    /// This is a convenience tied to the current storage layout, and may be
+    /// deprecated: prefer [`SortedVecMap::values_iter`].
+    #[inline]
+    pub fn values(&self) -> &[V] {
+        &self.values
+    }
+
+    /// Returns the values as a mutable slice, in key order.
+    ///
+    /// This is a convenience tied to the current storage layout, and may be
+    /// deprecated: prefer [`SortedVecMap::items_mut`].
+    #[inline]
+    pub fn values_mut(&mut self) -> &mut [V] {
+        &mut self.values
+    }
+
+    #[inline]
+    pub fn values_iter(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator {
+        self.values.iter()
+    }
+
+    #[inline]
+    pub fn items(&self) -> Iter<'_, K, V, Q> {
+        debug_assert_eq!(self.keys.len(), self.values.len());
+        Iter {
+            keys: self.keys.iter(),
+            values: self.values.iter(),
+            _borrowed: PhantomData,
+        }
+    }
+
+    /// Keys are only handed out immutably, so the sort order can't be broken.
+    #[inline]
+    pub fn items_mut(&mut self) -> IterMut<'_, K, V, Q> {
+        debug_assert_eq!(self.keys.len(), self.values.len());
+        IterMut {
+            keys: self.keys.iter(),
+            values: self.values.iter_mut(),
+            _borrowed: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V, Q> {
+        self.items_mut()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        debug_assert_eq!(self.keys.len(), self.values.len());
+        self.keys.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of entries the map can hold without reallocating.
+    /// The keys and values vectors may round their allocations differently,
+    /// so this is the smaller of their capacities.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.keys.capacity().min(self.values.capacity())
+    }
+
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.keys.reserve(additional);
+        self.values.reserve(additional);
+    }
+
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.keys.shrink_to_fit();
+        self.values.shrink_to_fit();
+    }
+
+    /// Removes every entry, keeping the allocations.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.values.clear();
+        self.unindex_all();
+    }
+
+    /// Like [`SortedVecMap::clear`], but moves the keys into `sink` instead of
+    /// dropping them, so that their allocations can be reused.
+    #[inline]
+    pub fn clear_recycle_keys(&mut self, sink: &mut Vec<K>) {
+        sink.append(&mut self.keys);
+        self.values.clear();
+        self.unindex_all();
+    }
+
+    /// Removes and returns the entry with the largest key.
+    #[inline]
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        let entry = (self.keys.pop()?, self.values.pop()?);
+        self.unindex(self.len());
+        Some(entry)
+    }
+
+    /// Removes and returns the entry with the smallest key.
+    #[inline]
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        if self.is_empty() {
+            return None;
+        }
+        self.unindex(0);
+        Some((self.keys.remove(0), self.values.remove(0)))
+    }
+
+    /// Empties the map, yielding its entries in key order. Entries that are not
+    /// consumed are dropped along with the iterator. The capacities of the map
+    /// are preserved.
+    pub fn drain(&mut self) -> impl Iterator<Item = (K, V)> + '_ {
+        self.unindex_all();
+        self.keys.drain(..).zip(self.values.drain(..))
+    }
+
+    /// Records in the hash index, if any, that a key with `hash` was inserted
+    /// at position `n`.
+    #[inline]
+    fn index(&mut self, n: usize, hash: Option<u64>) {
+        if let (Some(index), Some(hash)) = (&mut self.hash_index, hash) {
+            index.insert(hash, n);
+        }
+    }
+
+    /// Records in the hash index, if any, that the key at position `n` is
+    /// being removed.
+    #[inline]
+    fn unindex(&mut self, n: usize) {
+        if let Some(index) = &mut self.hash_index {
+            index.remove(n);
+        }
+    }
+
+    #[inline]
+    fn unindex_all(&mut self) {
+        if let Some(index) = &mut self.hash_index {
+            index.entries.clear();
+        }
+    }
+}
+
+impl<K, V, Q: Hash + ?Sized> SortedVecMap<K, V, Q> {
+    /// Like [`SortedVecMap::with_hash_index_by`], hashing the keys with the
+    /// standard library's default hasher.
+    ///
+    /// ```
+    /// use containers::StringMap;
+    ///
+    /// let mut map = StringMap::with_hash_index();
+    /// map.insert("key".to_string(), 1);
+    /// assert_eq!(map.get("key"), Some(&1));
+    /// ```
+    #[inline]
+    pub fn with_hash_index() -> Self {
+        Self::with_hash_index_by(default_hash::<Q>)
+    }
+}
+
+impl<K: Borrow<Q>, V, Q: Ord + ?Sized> SortedVecMap<K, V, Q> {
+    /// Builds a map from an iterator that yields strictly increasing keys, in O(n).
+    ///
+    /// The ordering is trusted, and only checked in debug builds.
+    pub fn from_sorted_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let (keys, values): (Vec<K>, Vec<V>) = iter.into_iter().unzip();
+        debug_assert!(keys.windows(2).all(|w| w[0].borrow() < w[1].borrow()));
+        Self {
+            keys,
+            values,
+            hash_index: None,
+            _borrowed: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn keys_iter(&self) -> impl DoubleEndedIterator<Item = &Q> + ExactSizeIterator {
+        self.keys.iter().map(|k| k.borrow())
+    }
+
+    #[inline]
+    pub fn key_idx<R: Borrow<Q> + ?Sized>(&self, key: &R) -> KeyIndex {
+        let key = key.borrow();
+        self.key_idx_hashed(key, self.hash(key))
+    }
+
+    /// Hashes `key` for the hash index, if there is one.
+    #[inline]
+    fn hash(&self, key: &Q) -> Option<u64> {
+        self.hash_index.as_ref().map(|index| (index.hasher)(key))
+    }
+
+    /// Looks `key` up, whose hash is `hash`, among the keys sharing its hash
+    /// before resorting to a binary search.
+    #[inline]
+    fn key_idx_hashed(&self, key: &Q, hash: Option<u64>) -> KeyIndex {
+        if let (Some(index), Some(hash)) = (&self.hash_index, hash) {
+            if let Some(n) = index
+                .positions(hash)
+                .find(|&n| self.keys[n].borrow() == key)
+            {
+                return Ok(n);
+            }
+        }
+        self.keys.binary_search_by(|k| k.borrow().cmp(key))
+    }
+
+    /// Rebuilds the hash index, if any, from the keys.
+    fn reindex(&mut self) {
+        if let Some(index) = &mut self.hash_index {
+            index.rebuild(self.keys.iter().map(|k| k.borrow()));
+        }
+    }
+
+    #[inline]
+    pub fn contains_key<R: Borrow<Q> + ?Sized>(&self, key: &R) -> bool {
+        self.key_idx(key).is_present()
+    }
+
+    /// Inserts `value` at `key`, returning the previous value if any. When the
+    /// key is already present, the stored key is kept and `key` is dropped.
+    #[inline]
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Like [`SortedVecMap::insert`], but also hands `key` back when it was
+    /// already present, so that its allocation can be reused.
+    #[inline]
+    pub fn insert_full(&mut self, key: K, value: V) -> (Option<K>, Option<V>) {
+        let hash = self.hash(key.borrow());
+        match self.key_idx_hashed(key.borrow(), hash) {
+            Ok(n) => (
+                Some(key),
+                Some(std::mem::replace(&mut self.values[n], value)),
+            ),
+            Err(n) => {
+                self.keys.insert(n, key);
+                self.values.insert(n, value);
+                self.index(n, hash);
+                (None, None)
+            }
+        }
+    }
+
+    /// Like [`SortedVecMap::insert`], but takes the key by reference and only
+    /// converts it to its owned form when it is not present yet.
+    #[inline]
+    pub fn insert_ref(&mut self, key: &Q, value: V) -> Option<V>
+    where
+        Q: ToOwned<Owned = K>,
+    {
+        let hash = self.hash(key);
+        match self.key_idx_hashed(key, hash) {
+            Ok(n) => Some(std::mem::replace(&mut self.values[n], value)),
+            Err(n) => {
+                self.keys.insert(n, key.to_owned());
+                self.values.insert(n, value);
+                self.index(n, hash);
+                None
+            }
+        }
+    }
+
+    #[inline]
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, Q> {
+        let hash = self.hash(key.borrow());
+        match self.key_idx_hashed(key.borrow(), hash) {
+            Ok(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            Err(index) => Entry::Vacant(VacantEntry {
+                map: self,
+                index,
+                key,
+                hash,
+            }),
+        }
+    }
+
+    /// Like [`SortedVecMap::entry`], but takes the key by reference. The key
+    /// is only converted to its owned form when a vacant entry is filled.
+    #[inline]
+    pub fn entry_ref<'k>(&mut self, key: &'k Q) -> EntryRef<'_, 'k, K, V, Q> {
+        let hash = self.hash(key);
+        match self.key_idx_hashed(key, hash) {
+            Ok(index) => EntryRef::Occupied(OccupiedEntry { map: self, index }),
+            Err(index) => EntryRef::Vacant(VacantEntryRef {
+                map: self,
+                index,
+                key,
+                hash,
+            }),
+        }
+    }
+
+    #[inline]
+    pub fn remove<R: Borrow<Q> + ?Sized>(&mut self, key: &R) -> Option<V> {
+        self.key_idx(key).ok().map(|n| {
+            self.unindex(n);
+            self.keys.remove(n);
+            self.values.remove(n)
+        })
+    }
+
+    #[inline]
+    pub fn get<R: Borrow<Q> + ?Sized>(&self, key: &R) -> Option<&V> {
+        self.key_idx(key).ok().map(|n| &self.values[n])
+    }
+
+    #[inline]
+    pub fn get_mut<R: Borrow<Q> + ?Sized>(&mut self, key: &R) -> Option<&mut V> {
+        self.key_idx(key).ok().map(|n| &mut self.values[n])
+    }
+
+    /// Returns the stored key along with its value.
+    #[inline]
+    pub fn get_key_value<R: Borrow<Q> + ?Sized>(&self, key: &R) -> Option<(&Q, &V)> {
+        self.key_idx(key)
+            .ok()
+            .map(|n| (self.keys[n].borrow(), &self.values[n]))
+    }
+
+    /// Returns the value at `key`, inserting the result of `f` first if there
+    /// is none. The key is only converted to its owned form when actually
+    /// inserting.
+    #[inline]
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: &Q, f: F) -> &mut V
+    where
+        Q: ToOwned<Owned = K>,
+    {
+        let hash = self.hash(key);
+        let n = match self.key_idx_hashed(key, hash) {
+            Ok(n) => n,
+            Err(n) => {
+                self.keys.insert(n, key.to_owned());
+                self.values.insert(n, f());
+                self.index(n, hash);
+                n
+            }
+        };
+        &mut self.values[n]
+    }
+
+    /// Returns the position of `key` in the map's sorted order.
+    ///
+    /// Positions are not stable: inserting or removing a smaller key shifts
+    /// every position after it.
+    #[inline]
+    pub fn index_of<R: Borrow<Q> + ?Sized>(&self, key: &R) -> Option<usize> {
+        self.key_idx(key).ok()
+    }
+
+    /// Returns the `i`-th entry in key order.
+    ///
+    /// Positions are not stable: inserting or removing a smaller key shifts
+    /// every position after it.
+    #[inline]
+    pub fn get_index(&self, i: usize) -> Option<(&Q, &V)> {
+        Some((self.keys.get(i)?.borrow(), self.values.get(i)?))
+    }
+
+    #[inline]
+    pub fn get_index_mut(&mut self, i: usize) -> Option<(&Q, &mut V)> {
+        Some((self.keys.get(i)?.borrow(), self.values.get_mut(i)?))
+    }
+
+    /// Removes the `i`-th entry in key order, shifting every later position.
+    #[inline]
+    pub fn remove_index(&mut self, i: usize) -> Option<(K, V)> {
+        if i >= self.len() {
+            return None;
+        }
+        self.unindex(i);
+        Some((self.keys.remove(i), self.values.remove(i)))
+    }
+
+    /// Returns the number of keys strictly smaller than `key`.
+    #[inline]
+    pub fn rank<R: Borrow<Q> + ?Sized>(&self, key: &R) -> usize {
+        match self.key_idx(key) {
+            Ok(n) | Err(n) => n,
+        }
+    }
+
+    /// Returns the entry with the `k`-th smallest key, counting from 0.
+    #[inline]
+    pub fn select(&self, k: usize) -> Option<(&Q, &V)> {
+        self.get_index(k)
+    }
+
+    /// Returns the number of keys within `range`, with two binary searches.
+    #[inline]
+    pub fn range_len<'a, R: RangeBounds<&'a Q>>(&self, range: R) -> usize
+    where
+        Q: 'a,
+    {
+        let (start, end) = self.range_bounds(range);
+        end - start
+    }
+
+    /// Returns the entry with the smallest key.
+    #[inline]
+    pub fn first(&self) -> Option<(&Q, &V)> {
+        Some((self.keys.first()?.borrow(), self.values.first()?))
+    }
+
+    /// Returns the entry with the largest key.
+    #[inline]
+    pub fn last(&self) -> Option<(&Q, &V)> {
+        Some((self.keys.last()?.borrow(), self.values.last()?))
+    }
+
+    /// Keeps only the entries for which `f` returns `true`, in a single O(n)
+    /// pass. The capacities of the map are preserved.
+    pub fn retain<F: FnMut(&Q, &mut V) -> bool>(&mut self, f: F) {
+        let keep = self.retain_mask(f);
+        let mut keep_keys = keep.iter();
+        self.keys.retain(|_| *keep_keys.next().unwrap());
+        let mut keep_values = keep.iter();
+        self.values.retain(|_| *keep_values.next().unwrap());
+        self.reindex();
+    }
+
+    /// Evaluates `f` on every entry before anything is moved, so that a panic
+    /// in `f` leaves the map untouched.
+    fn retain_mask<F: FnMut(&Q, &mut V) -> bool>(&mut self, mut f: F) -> Vec<bool> {
+        self.keys
+            .iter()
+            .zip(self.values.iter_mut())
+            .map(|(k, v)| f(k.borrow(), v))
+            .collect()
+    }
+
+    /// Removes the entries for which `f` returns `true`, and returns them in
+    /// key order, in a single O(n) pass.
+    pub fn remove_matching<F: FnMut(&Q, &mut V) -> bool>(&mut self, mut f: F) -> Vec<(K, V)> {
+        let extract = self.retain_mask(|k, v| f(k, v));
+        let removed = extract.iter().filter(|e| **e).count();
+
+        let capacity = (self.keys.capacity(), self.values.capacity());
+        let keys = std::mem::replace(&mut self.keys, Vec::with_capacity(capacity.0));
+        let values = std::mem::replace(&mut self.values, Vec::with_capacity(capacity.1));
+
+        let mut extracted = Vec::with_capacity(removed);
+        for ((key, value), extract) in keys.into_iter().zip(values).zip(extract) {
+            if extract {
+                extracted.push((key, value));
+            } else {
+                self.keys.push(key);
+                self.values.push(value);
+            }
+        }
+        self.reindex();
+        extracted
+    }
+
+    /// Returns the bounds in `self.keys` of the keys within `range`.
+    fn range_bounds<'a, R: RangeBounds<&'a Q>>(&self, range: R) -> (usize, usize)
+    where
+        Q: 'a,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(s) => self.keys.partition_point(|k| k.borrow() < *s),
+            Bound::Excluded(s) => self.keys.partition_point(|k| k.borrow() <= *s),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(e) => self.keys.partition_point(|k| k.borrow() <= *e),
+            Bound::Excluded(e) => self.keys.partition_point(|k| k.borrow() < *e),
+            Bound::Unbounded => self.keys.len(),
+        };
+        (start, end.max(start))
+    }
+
+    /// Iterates, in order, over the entries whose key is within `range`, like
+    /// `BTreeMap::range`. A range whose start is past its end is empty.
+    ///
+    /// ```
+    /// use containers::StringMap;
+    ///
+    /// let mut map = StringMap::new();
+    /// for key in ["a", "b", "c", "d"] {
+    ///     map.insert(key.to_string(), 0);
+    /// }
+    ///
+    /// let keys = map.range("b"..="c").map(|(k, _)| k).collect::<Vec<_>>();
+    /// assert_eq!(keys, vec!["b", "c"]);
+    /// ```
+    pub fn range<'a, R: RangeBounds<&'a Q>>(&self, range: R) -> impl Iterator<Item = (&Q, &V)>
+    where
+        Q: 'a,
+    {
+        let (start, end) = self.range_bounds(range);
+        self.slice_items(start, end)
+    }
+
+    pub fn range_mut<'a, R: RangeBounds<&'a Q>>(
+        &mut self,
+        range: R,
+    ) -> impl Iterator<Item = (&Q, &mut V)>
+    where
+        Q: 'a,
+    {
+        let (start, end) = self.range_bounds(range);
+        self.slice_items_mut(start, end)
+    }
+
+    /// Moves every entry whose key is greater than or equal to `key` into a new
+    /// map, with a single binary search.
+    pub fn split_off<R: Borrow<Q> + ?Sized>(&mut self, key: &R) -> Self {
+        let key = key.borrow();
+        let n = self.keys.partition_point(|k| k.borrow() < key);
+        let mut other = Self {
+            keys: self.keys.split_off(n),
+            values: self.values.split_off(n),
+            hash_index: self
+                .hash_index
+                .as_ref()
+                .map(|index| HashIndex::new(index.hasher)),
+            _borrowed: PhantomData,
+        };
+        self.reindex();
+        other.reindex();
+        other
+    }
+
+    /// Moves every entry of `other` into `self`, leaving `other` empty. Both
+    /// maps are merged in place in O(n + m), and the values of `other` win on
+    /// duplicate keys.
+    pub fn append(&mut self, other: &mut Self) {
+        other.unindex_all();
+        self.merge_sorted(&mut other.keys, &mut other.values);
+    }
+
+    /// Merges `keys` and `values`, whose keys are strictly increasing, into the
+    /// map's own buffers, leaving them empty. Values from the batch win on
+    /// duplicate keys.
+    ///
+    /// Every comparison is made before anything moves, so a panic in `Ord`
+    /// leaves the map untouched. The batch is then appended, and the entries
+    /// are swapped into place along the cycles of the merge permutation.
+    fn merge_sorted(&mut self, keys: &mut Vec<K>, values: &mut Vec<V>) {
+        let order = match (self.keys.last(), keys.first()) {
+            (Some(last), Some(first)) if last.borrow() >= first.borrow() => {
+                Some(self.merge_order(keys))
+            }
+            // Appending in order needs no reordering.
+            _ => None,
+        };
+        self.keys.append(keys);
+        self.values.append(values);
+
+        if let Some((mut order, len)) = order {
+            const DONE: usize = usize::MAX;
+            for start in 0..order.len() {
+                let mut n = start;
+                while order[n] != DONE {
+                    let source = std::mem::replace(&mut order[n], DONE);
+                    if source != start {
+                        self.keys.swap(n, source);
+                        self.values.swap(n, source);
+                        n = source;
+                    }
+                }
+            }
+            // The entries shadowed by the batch were moved past `len`.
+            self.keys.truncate(len);
+            self.values.truncate(len);
+        }
+        self.reindex();
+    }
+
+    /// Returns, for each position of the merged map, the position its entry
+    /// will come from once `batch` is appended, followed by the positions of
+    /// the entries replaced by `batch`. Also returns the merged length.
+    fn merge_order(&self, batch: &[K]) -> (Vec<usize>, usize) {
+        let (old, new) = (self.keys.len(), batch.len());
+        let mut order = Vec::with_capacity(old + new);
+        let mut replaced = vec![];
+
+        let (mut i, mut j) = (0, 0);
+        while i < old && j < new {
+            match self.keys[i].borrow().cmp(batch[j].borrow()) {
+                std::cmp::Ordering::Less => {
+                    order.push(i);
+                    i += 1;
+                }
+                std::cmp::Ordering::Greater => {
+                    order.push(old + j);
+                    j += 1;
+                }
+                std::cmp::Ordering::Equal => {
+                    replaced.push(i);
+                    order.push(old + j);
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        order.extend(i..old);
+        order.extend(old + j..old + new);
+
+        let len = order.len();
+        order.extend(replaced);
+        (order, len)
+    }
+
+    /// Walks both maps together in key order, telling for each key whether
+    /// it is in `self`, in `other`, or in both. This is a linear merge over the
+    /// two sorted key vectors.
+    pub fn merge_join<'a, U>(
+        &'a self,
+        other: &'a SortedVecMap<K, U, Q>,
+    ) -> impl Iterator<Item = (&'a Q, EitherOrBoth<&'a V, &'a U>)> {
+        let mut left = self.items().peekable();
+        let mut right = other.items().peekable();
+        std::iter::from_fn(move || {
+            let order = match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) => l.0.cmp(r.0),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => return None,
+            };
+            Some(match order {
+                std::cmp::Ordering::Less => {
+                    let (k, v) = left.next()?;
+                    (k, EitherOrBoth::Left(v))
+                }
+                std::cmp::Ordering::Greater => {
+                    let (k, u) = right.next()?;
+                    (k, EitherOrBoth::Right(u))
+                }
+                std::cmp::Ordering::Equal => {
+                    let (k, v) = left.next()?;
+                    let (_, u) = right.next()?;
+                    (k, EitherOrBoth::Both(v, u))
+                }
+            })
+        })
+    }
+
+    /// Iterates, in order, over the keys present in exactly one of the maps.
+    pub fn diff_keys<'a, U>(
+        &'a self,
+        other: &'a SortedVecMap<K, U, Q>,
+    ) -> impl Iterator<Item = &'a Q> {
+        self.merge_join(other)
+            .filter(|(_, e)| !e.is_both())
+            .map(|(k, _)| k)
+    }
+
+    /// Iterates over the entries between positions `start` and `end`.
+    pub(crate) fn slice_items(&self, start: usize, end: usize) -> impl Iterator<Item = (&Q, &V)> {
+        self.keys[start..end]
+            .iter()
+            .map(|k| k.borrow())
+            .zip(self.values[start..end].iter())
+    }
+
+    /// Iterates mutably over the entries between positions `start` and `end`.
+    pub(crate) fn slice_items_mut(
+        &mut self,
+        start: usize,
+        end: usize,
+    ) -> impl Iterator<Item = (&Q, &mut V)> {
+        self.keys[start..end]
+            .iter()
+            .map(|k| k.borrow())
+            .zip(self.values[start..end].iter_mut())
+    }
+}
+
+/// A value present on the left side, the right side, or both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EitherOrBoth<L, R> {
+    Left(L),
+    Right(R),
+    Both(L, R),
+}
+
+impl<L, R> EitherOrBoth<L, R> {
+    #[inline]
+    pub fn is_both(&self) -> bool {
+        matches!(self, EitherOrBoth::Both(..))
+    }
+
+    #[inline]
+    pub fn left(self) -> Option<L> {
+        match self {
+            EitherOrBoth::Left(l) | EitherOrBoth::Both(l, _) => Some(l),
+            EitherOrBoth::Right(_) => None,
+        }
+    }
+
+    #[inline]
+    pub fn right(self) -> Option<R> {
+        match self {
+            EitherOrBoth::Right(r) | EitherOrBoth::Both(_, r) => Some(r),
+            EitherOrBoth::Left(_) => None,
+        }
+    }
+}
+
+/// A view into a single entry of a [`SortedVecMap`], which may be vacant or
+/// occupied.
+pub enum Entry<'a, K, V, Q: ?Sized> {
+    Occupied(OccupiedEntry<'a, K, V, Q>),
+    Vacant(VacantEntry<'a, K, V, Q>),
+}
+
+pub struct OccupiedEntry<'a, K, V, Q: ?Sized> {
+    map: &'a mut SortedVecMap<K, V, Q>,
+    index: usize,
+}
+
+/// The position of the key is already known, so inserting only shifts the
+/// vectors, without another binary search.
+pub struct VacantEntry<'a, K, V, Q: ?Sized> {
+    map: &'a mut SortedVecMap<K, V, Q>,
+    index: usize,
+    key: K,
+    hash: Option<u64>,
+}
+
+impl<'a, K, V, Q: ?Sized> Entry<'a, K, V, Q> {
+    #[inline]
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    #[inline]
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    #[inline]
+    pub fn or_insert_with_key<F: FnOnce(&K) -> V>(self, f: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = f(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    #[inline]
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    #[inline]
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K, V, Q: ?Sized> OccupiedEntry<'a, K, V, Q> {
+    #[inline]
+    pub fn key(&self) -> &K {
+        &self.map.keys[self.index]
+    }
+
+    /// Returns the position of the entry in the map's sorted vectors.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    #[inline]
+    pub fn get(&self) -> &V {
+        &self.map.values[self.index]
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.values[self.index]
+    }
+
+    #[inline]
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.values[self.index]
+    }
+
+    /// Replaces the value, returning the previous one. The stored key is kept.
+    #[inline]
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    #[inline]
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    #[inline]
+    pub fn remove_entry(self) -> (K, V) {
+        self.map.unindex(self.index);
+        (
+            self.map.keys.remove(self.index),
+            self.map.values.remove(self.index),
+        )
+    }
+}
+
+impl<'a, K, V, Q: ?Sized> VacantEntry<'a, K, V, Q> {
+    #[inline]
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    #[inline]
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Returns the position the entry will have in the map's sorted vectors.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    #[inline]
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.map.keys.insert(self.index, self.key);
+        self.map.values.insert(self.index, value);
+        self.map.index(self.index, self.hash);
+        &mut self.map.values[self.index]
+    }
+}
+
+/// A view into a single entry of a [`SortedVecMap`], obtained from a borrowed
+/// key with [`SortedVecMap::entry_ref`].
+pub enum EntryRef<'a, 'k, K, V, Q: ?Sized> {
+    Occupied(OccupiedEntry<'a, K, V, Q>),
+    Vacant(VacantEntryRef<'a, 'k, K, V, Q>),
+}
+
+/// A vacant entry holding a borrowed key, which is only converted to its
+/// owned form on insertion.
+pub struct VacantEntryRef<'a, 'k, K, V, Q: ?Sized> {
+    map: &'a mut SortedVecMap<K, V, Q>,
+    index: usize,
+    key: &'k Q,
+    hash: Option<u64>,
+}
+
+impl<'a, K: Borrow<Q>, V, Q: ToOwned<Owned = K> + ?Sized> EntryRef<'a, '_, K, V, Q> {
+    #[inline]
+    pub fn key(&self) -> &Q {
+        match self {
+            EntryRef::Occupied(entry) => entry.key().borrow(),
+            EntryRef::Vacant(entry) => entry.key(),
+        }
+    }
+
+    #[inline]
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> &'a mut V {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    #[inline]
+    pub fn or_insert_with_key<F: FnOnce(&Q) -> V>(self, f: F) -> &'a mut V {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => {
+                let value = f(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    #[inline]
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    #[inline]
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let EntryRef::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, 'k, K, V, Q: ToOwned<Owned = K> + ?Sized> VacantEntryRef<'a, 'k, K, V, Q> {
+    #[inline]
+    pub fn key(&self) -> &'k Q {
+        self.key
+    }
+
+    /// Returns the position the entry will have in the map's sorted vectors.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Converts the key to its owned form and inserts it along with `value`.
+    #[inline]
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.map.keys.insert(self.index, self.key.to_owned());
+        self.map.values.insert(self.index, value);
+        self.map.index(self.index, self.hash);
+        &mut self.map.values[self.index]
+    }
+}
+
+impl<K: Borrow<Q>, V: std::fmt::Debug, Q: std::fmt::Debug + ?Sized> std::fmt::Debug
+    for SortedVecMap<K, V, Q>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.items()).finish()
+    }
+}
+
+/// Iterator over the entries of a [`SortedVecMap`], in key order.
+pub struct Iter<'a, K, V, Q: ?Sized> {
+    keys: std::slice::Iter<'a, K>,
+    values: std::slice::Iter<'a, V>,
+    _borrowed: PhantomData<fn(&Q)>,
+}
+
+impl<K, V, Q: ?Sized> Clone for Iter<'_, K, V, Q> {
+    fn clone(&self) -> Self {
+        Self {
+            keys: self.keys.clone(),
+            values: self.values.clone(),
+            _borrowed: PhantomData,
+        }
+    }
+}
+
+impl<'a, K: Borrow<Q>, V, Q: ?Sized + 'a> Iterator for Iter<'a, K, V, Q> {
+    type Item = (&'a Q, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next()?.borrow(), self.values.next()?))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<'a, K: Borrow<Q>, V, Q: ?Sized + 'a> DoubleEndedIterator for Iter<'a, K, V, Q> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next_back()?.borrow(), self.values.next_back()?))
+    }
+}
+
+impl<'a, K: Borrow<Q>, V, Q: ?Sized + 'a> ExactSizeIterator for Iter<'a, K, V, Q> {}
+
+impl<'a, K: Borrow<Q>, V, Q: ?Sized + 'a> FusedIterator for Iter<'a, K, V, Q> {}
+
+/// Mutable iterator over the entries of a [`SortedVecMap`], in key order.
+pub struct IterMut<'a, K, V, Q: ?Sized> {
+    keys: std::slice::Iter<'a, K>,
+    values: std::slice::IterMut<'a, V>,
+    _borrowed: PhantomData<fn(&Q)>,
+}
+
+impl<'a, K: Borrow<Q>, V, Q: ?Sized + 'a> Iterator for IterMut<'a, K, V, Q> {
+    type Item = (&'a Q, &'a mut V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next()?.borrow(), self.values.next()?))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<'a, K: Borrow<Q>, V, Q: ?Sized + 'a> DoubleEndedIterator for IterMut<'a, K, V, Q> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next_back()?.borrow(), self.values.next_back()?))
+    }
+}
+
+impl<'a, K: Borrow<Q>, V, Q: ?Sized + 'a> ExactSizeIterator for IterMut<'a, K, V, Q> {}
+
+impl<'a, K: Borrow<Q>, V, Q: ?Sized + 'a> FusedIterator for IterMut<'a, K, V, Q> {}
+
+/// Owning iterator over the entries of a [`SortedVecMap`], in key order.
+pub struct IntoIter<K, V> {
+    keys: std::vec::IntoIter<K>,
+    values: std::vec::IntoIter<V>,
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next()?, self.values.next()?))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next_back()?, self.values.next_back()?))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K, V> FusedIterator for IntoIter<K, V> {}
+
+impl<'a, K: Borrow<Q>, V, Q: ?Sized + 'a> IntoIterator for &'a SortedVecMap<K, V, Q> {
+    type Item = (&'a Q, &'a V);
+    type IntoIter = Iter<'a, K, V, Q>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items()
+    }
+}
+
+impl<'a, K: Borrow<Q>, V, Q: ?Sized + 'a> IntoIterator for &'a mut SortedVecMap<K, V, Q> {
+    type Item = (&'a Q, &'a mut V);
+    type IntoIter = IterMut<'a, K, V, Q>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items_mut()
+    }
+}
+
+impl<K, V, Q: ?Sized> IntoIterator for SortedVecMap<K, V, Q> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            keys: self.keys.into_iter(),
+            values: self.values.into_iter(),
+        }
+    }
+}
+
+/// Collects `iter`, sorted by key, keeping only the last value for each key.
+fn sorted_batch<K, V, Q, I>(iter: I) -> Vec<(K, V)>
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+    I: IntoIterator<Item = (K, V)>,
+{
+    let mut batch = iter.into_iter().collect::<Vec<_>>();
+    // Stable sort, so that duplicates stay in insertion order.
+    batch.sort_by(|a, b| a.0.borrow().cmp(b.0.borrow()));
+
+    let mut deduped: Vec<(K, V)> = Vec::with_capacity(batch.len());
+    for (key, value) in batch {
+        match deduped.last_mut() {
+            Some(last) if last.0.borrow() == key.borrow() => last.1 = value,
+            _ => deduped.push((key, value)),
+        }
+    }
+    deduped
+}
+
+impl<K: Borrow<Q>, V, Q: Ord + ?Sized> FromIterator<(K, V)> for SortedVecMap<K, V, Q> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::from_sorted_iter(sorted_batch(iter))
+    }
+}
+
+impl<K: Borrow<Q>, V, Q: Ord + ?Sized> Extend<(K, V)> for SortedVecMap<K, V, Q> {
+    /// Sorts the incoming batch, then merges it in place with the existing
+    /// entries in a single pass. Incoming values win on duplicate keys.
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let (mut keys, mut values): (Vec<K>, Vec<V>) = sorted_batch(iter).into_iter().unzip();
+        self.merge_sorted(&mut keys, &mut values);
+    }
+}
+
+impl<K, V, Q, R> Index<&R> for SortedVecMap<K, V, Q>
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+    R: Borrow<Q> + ?Sized,
+{
+    type Output = V;
+
+    fn index(&self, key: &R) -> &V {
+        self.get(key).unwrap()
+    }
+}
+
+impl<K, V, Q, R> IndexMut<&R> for SortedVecMap<K, V, Q>
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+    R: Borrow<Q> + ?Sized,
+{
+    fn index_mut(&mut self, key: &R) -> &mut V {
+        self.get_mut(key).unwrap()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K: serde::Serialize, V: serde::Serialize, Q: ?Sized> serde::Serialize
+    for SortedVecMap<K, V, Q>
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in self.keys.iter().zip(self.values.iter()) {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V, Q> serde::Deserialize<'de> for SortedVecMap<K, V, Q>
+where
+    K: serde::Deserialize<'de> + Borrow<Q>,
+    V: serde::Deserialize<'de>,
+    Q: Ord + ?Sized,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor<K, V, Q: ?Sized>(PhantomData<(K, V)>, PhantomData<fn(&Q)>);
+
+        impl<'de, K, V, Q> serde::de::Visitor<'de> for Visitor<K, V, Q>
+        where
+            K: serde::Deserialize<'de> + Borrow<Q>,
+            V: serde::Deserialize<'de>,
+            Q: Ord + ?Sized,
+        {
+            type Value = SortedVecMap<K, V, Q>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut access: A,
+            ) -> Result<Self::Value, A::Error> {
+                // The hint comes from the input, so it is only trusted up to a
+                // megabyte of preallocation.
+                const MAX_PREALLOC_BYTES: usize = 1024 * 1024;
+                let cap = MAX_PREALLOC_BYTES / std::mem::size_of::<(K, V)>().max(1);
+                let mut entries = Vec::with_capacity(access.size_hint().unwrap_or(0).min(cap));
+                while let Some(entry) = access.next_entry()? {
+                    entries.push(entry);
+                }
+                // Sorted in one go, duplicate keys keep the last value.
+                Ok(entries.into_iter().collect())
+            }
+        }
+
+        deserializer.deserialize_map(Visitor(PhantomData, PhantomData))
+    }
+}
+
+/// The hashes of the keys of a map, along with their positions.
+struct HashIndex<Q: ?Sized> {
+    hasher: fn(&Q) -> u64,
+    /// `(hash, position)` pairs, sorted.
+    entries: Vec<(u64, usize)>,
+}
+
+impl<Q: ?Sized> Clone for HashIndex<Q> {
+    fn clone(&self) -> Self {
+        Self {
+            hasher: self.hasher,
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<Q: ?Sized> HashIndex<Q> {
+    #[inline]
+    fn new(hasher: fn(&Q) -> u64) -> Self {
+        Self {
+            hasher,
+            entries: vec![],
+        }
+    }
+
+    /// Iterates over the positions of the keys whose hash is `hash`.
+    #[inline]
+    fn positions(&self, hash: u64) -> impl Iterator<Item = usize> + '_ {
+        let start = self.entries.partition_point(|e| e.0 < hash);
+        self.entries[start..]
+            .iter()
+            .take_while(move |e| e.0 == hash)
+            .map(|e| e.1)
+    }
+
+    /// Shifts the positions from `n` on, then records the key at `n`.
+    fn insert(&mut self, hash: u64, n: usize) {
+        for entry in self.entries.iter_mut().filter(|e| e.1 >= n) {
+            entry.1 += 1;
+        }
+        let at = self.entries.partition_point(|e| *e < (hash, n));
+        self.entries.insert(at, (hash, n));
+    }
+
+    /// Forgets the key at `n`, then shifts the positions after it.
+    fn remove(&mut self, n: usize) {
+        self.entries.retain(|e| e.1 != n);
+        for entry in self.entries.iter_mut().filter(|e| e.1 > n) {
+            entry.1 -= 1;
+        }
+    }
+
+    fn rebuild<'a>(&mut self, keys: impl Iterator<Item = &'a Q>)
+    where
+        Q: 'a,
+    {
+        self.entries.clear();
+        self.entries
+            .extend(keys.enumerate().map(|(n, k)| ((self.hasher)(k), n)));
+        self.entries.sort_unstable();
+    }
+}
+
+fn default_hash<Q: Hash + ?Sized>(key: &Q) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+type KeyIndex = Result<usize, usize>;
+trait KeyIndexProps {
+    fn is_present(&self) -> bool;
+}
+impl KeyIndexProps for KeyIndex {
+    fn is_present(&self) -> bool {
+        self.is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sorted_vec_map::Entry;
+    use crate::SortedVecMap;
+    use crate::StringMap;
+    use std::fmt::Debug;
+    use std::ops::Bound;
+    use typed_test_gen::test_with;
+
+    type Pair = (u32, u32);
+
+    trait TestKey: Ord + Clone + Debug {
+        fn key(i: usize) -> Self;
+    }
+
+    impl TestKey for usize {
+        fn key(i: usize) -> Self {
+            i
+        }
+    }
+
+    impl TestKey for String {
+        fn key(i: usize) -> Self {
+            i.to_string()
+        }
+    }
+
+    impl TestKey for Pair {
+        fn key(i: usize) -> Self {
+            ((i % 7) as u32, i as u32)
+        }
+    }
+
+    fn sorted_keys<K: TestKey>(n: usize) -> Vec<K> {
+        let mut keys = (0..n).map(K::key).collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    #[test_with(usize, String, Pair)]
+    fn insert_get_remove<K: TestKey>() {
+        let mut map = SortedVecMap::<K, usize, K>::new();
+        for i in (0..20).rev() {
+            assert_eq!(map.insert(K::key(i), i), None);
+        }
+        assert_eq!(map.len(), 20);
+        assert_eq!(map.keys(), sorted_keys::<K>(20));
+
+        for i in 0..20 {
+            assert!(map.contains_key(&K::key(i)));
+            assert_eq!(map.get(&K::key(i)), Some(&i));
+            assert_eq!(map[&K::key(i)], i);
+        }
+        assert!(!map.contains_key(&K::key(20)));
+
+        assert_eq!(map.insert(K::key(3), 30), Some(3));
+        *map.get_mut(&K::key(4)).unwrap() = 40;
+        map[&K::key(5)] = 50;
+        assert_eq!(map[&K::key(3)], 30);
+        assert_eq!(map[&K::key(4)], 40);
+        assert_eq!(map[&K::key(5)], 50);
+
+        for i in 0..10 {
+            assert!(map.remove(&K::key(i)).is_some());
+            assert!(map.remove(&K::key(i)).is_none());
+        }
+        assert_eq!(map.len(), 10);
+        assert!(map.keys().windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test_with(usize, String, Pair)]
+    fn entry<K: TestKey>() {
+        let mut map = SortedVecMap::<K, usize, K>::new();
+        for i in 0..10 {
+            *map.entry(K::key(i % 5)).or_default() += 1;
+        }
+        assert_eq!(map.len(), 5);
+        assert!(map.values().iter().all(|v| *v == 2));
+
+        assert_eq!(*map.entry(K::key(7)).or_insert(7), 7);
+        assert_eq!(*map.entry(K::key(7)).or_insert(70), 7);
+        assert_eq!(*map.entry(K::key(8)).or_insert_with(|| 8), 8);
+        map.entry(K::key(8)).and_modify(|v| *v += 1).or_insert(0);
+        assert_eq!(map[&K::key(8)], 9);
+        assert_eq!(map.entry(K::key(9)).key(), &K::key(9));
+        let mut keys = [0, 1, 2, 3, 4, 7, 8].map(K::key).to_vec();
+        keys.sort();
+        assert_eq!(map.keys(), keys);
+
+        match map.entry(K::key(7)) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.insert(70), 7);
+                assert_eq!(entry.remove(), 70);
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert!(!map.contains_key(&K::key(7)));
+
+        match map.entry(K::key(7)) {
+            Entry::Occupied(_) => unreachable!(),
+            Entry::Vacant(entry) => *entry.insert(7) += 1,
+        }
+        assert_eq!(map[&K::key(7)], 8);
+    }
+
+    #[test_with(usize, String, Pair)]
+    fn iteration<K: TestKey>() {
+        let map = (0..20)
+            .rev()
+            .map(|i| (K::key(i), i))
+            .collect::<SortedVecMap<K, usize, K>>();
+        let keys = sorted_keys::<K>(20);
+
+        assert_eq!(map.keys_iter().cloned().collect::<Vec<_>>(), keys);
+        assert_eq!(
+            map.items().map(|(k, _)| k.clone()).collect::<Vec<_>>(),
+            keys
+        );
+        for (k, v) in &map {
+            assert_eq!(&K::key(*v), k);
+        }
+
+        let range = map.range(&keys[5]..&keys[10]).map(|(k, _)| k.clone());
+        assert_eq!(range.collect::<Vec<_>>(), keys[5..10]);
+
+        assert_eq!(map.into_iter().map(|(k, _)| k).collect::<Vec<_>>(), keys);
+    }
+
+    #[test_with(usize, String, Pair)]
+    fn order_statistics<K: TestKey>() {
+        let map = (0..40)
+            .step_by(2)
+            .map(|i| (K::key(i), i))
+            .collect::<SortedVecMap<K, usize, K>>();
+
+        for i in 0..45 {
+            let key = K::key(i);
+            let below = map.items().filter(|(k, _)| **k < key).count();
+            assert_eq!(map.rank(&key), below);
+            assert_eq!(map.select(i), map.items().nth(i));
+
+            for j in 0..45 {
+                let end = K::key(j);
+                let brute = map.items().filter(|(k, _)| **k >= key && **k < end).count();
+                assert_eq!(map.range_len(&key..&end), brute);
+                let brute = map.items().filter(|(k, _)| **k > key && **k <= end).count();
+                assert_eq!(
+                    map.range_len((Bound::Excluded(&key), Bound::Included(&end))),
+                    brute
+                );
+            }
+        }
+        assert_eq!(map.range_len(..), map.len());
+    }
+
+    #[test]
+    fn borrowed_lookups() {
+        let mut map = SortedVecMap::<String, usize, str>::new();
+        map.insert("a".to_string(), 1);
+
+        let owned = "a".to_string();
+        assert_eq!(map.get("a"), Some(&1));
+        assert_eq!(map.get(&owned), Some(&1));
+        assert_eq!(map["a"], 1);
+        assert_eq!(map[&owned], 1);
+    }
+
+    #[test]
+    fn hash_index_collisions() {
+        // Every key of a given length shares the same hash.
+        let mut map = StringMap::with_hash_index_by(|k: &str| k.len() as u64);
+        for key in ["b", "a", "ab", "c", "ba", "abc", "aa"] {
+            map.insert(key.to_string(), key.to_string());
+        }
+        let check = |map: &StringMap<String>| {
+            let mut rebuilt = map.hash_index.clone().unwrap();
+            rebuilt.rebuild(map.keys_iter());
+            assert_eq!(map.hash_index.as_ref().unwrap().entries, rebuilt.entries);
+            for (n, (key, value)) in map.items().enumerate() {
+                assert_eq!(key, value);
+                assert_eq!(map.key_idx(key), Ok(n));
+            }
+        };
+        check(&map);
+        assert!(!map.contains_key("d"));
+        assert_eq!(map.key_idx("bb"), Err(6));
+
+        assert_eq!(map.remove("ab").as_deref(), Some("ab"));
+        assert_eq!(map.remove("ab"), None);
+        check(&map);
+        map.entry("bb".to_string()).or_insert("bb".to_string());
+        map.insert_ref("d", "d".to_string());
+        map.get_or_insert_with("ca", || "ca".to_string());
+        check(&map);
+        assert_eq!(map.pop_first().unwrap().0, "a");
+        assert_eq!(map.pop_last().unwrap().0, "d");
+        assert_eq!(map.remove_index(1).unwrap().0, "abc");
+        check(&map);
+
+        map.retain(|k, _| k != "ba");
+        check(&map);
+        let mut high = map.split_off("bb");
+        check(&map);
+        check(&high);
+        assert_eq!(high.keys(), &["bb", "c", "ca"]);
+        assert!(high.contains_key("ca"));
+        assert!(!map.contains_key("ca"));
+
+        map.extend([("e", "e"), ("aa", "aa")].map(|(k, v)| (k.to_string(), v.to_string())));
+        map.append(&mut high);
+        check(&map);
+        check(&high);
+        assert_eq!(map.remove_matching(|k, _| k.len() == 1).len(), 3);
+        check(&map);
+        assert_eq!(map.keys(), &["aa", "bb", "ca"]);
+
+        let mut copy = map.clone();
+        copy.insert("ab".to_string(), "ab".to_string());
+        check(&copy);
+        map.clear();
+        check(&map);
+        map.extend([("z".to_string(), "z".to_string())]);
+        assert!(map.contains_key("z"));
+        assert_eq!(map.drain().count(), 1);
+        check(&map);
+    }
+}
@@ -79,9 +79,9 @@ impl<T> SparseVec<T> {
             let (_, value) = self.data.swap_remove(position);
             let ex_last = self.data[position].0;
             self.positions[ex_last] = self.positions[n];
-            self.positions[n] = 0;
             Some(value)
         };
+        self.positions[n] = 0;
         self.free_indices.push(n);
 
         deleted
@@ -129,12 +129,18 @@ impl<T> SparseVec<T> {
         Some(&mut self.data.get_mut(position)?.1)
     }
 
+    /// # Safety
+    ///
+    /// There must be an element at index `n`, see [`SparseVec::contains`].
     #[inline]
     pub unsafe fn get_unchecked(&self, n: usize) -> &T {
         let position = self.position_unchecked(n);
         &self.data.get_unchecked(position).1
     }
 
+    /// # Safety
+    ///
+    /// There must be an element at index `n`, see [`SparseVec::contains`].
     #[inline]
     pub unsafe fn get_unchecked_mut(&mut self, n: usize) -> &mut T {
         let position = self.position_unchecked(n);
@@ -156,6 +162,28 @@ impl<T> SparseVec<T> {
     pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
         self.data.iter_mut().map(|(_, val)| val)
     }
+
+    /// Iterates over the entries in increasing key order.
+    ///
+    /// This walks `positions`, which is indexed by key, skipping the empty
+    /// slots. It costs O(largest key ever used) instead of O(len) for
+    /// [`SparseVec::items`], and reads `data` out of order.
+    pub fn iter_sorted(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.positions
+            .iter()
+            .enumerate()
+            .filter(|(_, &p)| p > 0)
+            .map(|(n, &p)| (n, &self.data[p - 1].1))
+    }
+
+    /// Iterates over the keys in increasing order, see [`SparseVec::iter_sorted`].
+    pub fn keys_sorted(&self) -> impl Iterator<Item = usize> + '_ {
+        self.positions
+            .iter()
+            .enumerate()
+            .filter(|(_, &p)| p > 0)
+            .map(|(n, _)| n)
+    }
 }
 
 impl<T> Index<usize> for SparseVec<T> {
@@ -207,6 +235,9 @@ mod tests {
         let idx = set.insert(T::default());
         assert!(set.remove(idx).is_some());
         assert_eq!(set.len(), 0);
+        assert!(!set.contains(idx));
+        assert!(set.remove(idx).is_none());
+        assert_eq!(set.free_indices(), &[idx]);
     }
 
     #[test_with(usize, String, Dummy)]
@@ -318,6 +349,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sorted_iteration() {
+        let mut set = SparseVec::<usize>::new();
+        for i in 0..20 {
+            set.insert(i * 10);
+        }
+        for n in [3, 0, 17, 8, 19, 4] {
+            set.remove(n);
+        }
+        // Freed keys are reused, and removals shuffle `data`.
+        assert_eq!(set.insert(400), 4);
+        assert_eq!(set.insert(190), 19);
+        set.remove(10);
+        assert_eq!(set.insert(500), 10);
+        assert_eq!(set.insert(200), 8);
+
+        let mut expected = set.items().map(|&(n, v)| (n, v)).collect::<Vec<_>>();
+        expected.sort();
+        assert_ne!(
+            set.keys().collect::<Vec<_>>(),
+            set.keys_sorted().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            set.iter_sorted().map(|(n, &v)| (n, v)).collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(
+            set.keys_sorted().collect::<Vec<_>>(),
+            expected.iter().map(|e| e.0).collect::<Vec<_>>()
+        );
+
+        set.clear();
+        assert_eq!(set.iter_sorted().count(), 0);
+        assert_eq!(set.keys_sorted().count(), 0);
+    }
+
     #[test]
     fn clear() {
         let mut set = SparseVec::<usize>::new();
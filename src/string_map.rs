@@ -1,123 +1,141 @@
-use std::ops::Index;
-use std::ops::IndexMut;
+use crate::sorted_vec_map;
+use crate::SortedVecMap;
+use std::borrow::Borrow;
 
 /// This is meant to replace a HashMap<String, T> in every way
 /// It is a horrible idea and performs much worse in almost every case
-pub struct StringMap<T> {
-    keys: Vec<String>,
-    values: Vec<T>,
-}
+pub type StringMap<T> = SortedVecMap<String, T, str>;
 
-impl<T> Default for StringMap<T> {
-    fn default() -> Self {
-        Self {
-            keys: vec![],
-            values: vec![],
-        }
-    }
-}
+pub type Iter<'a, T> = sorted_vec_map::Iter<'a, String, T, str>;
+pub type IterMut<'a, T> = sorted_vec_map::IterMut<'a, String, T, str>;
+pub type IntoIter<T> = sorted_vec_map::IntoIter<String, T>;
+pub type EntryRef<'m, 'k, T> = sorted_vec_map::EntryRef<'m, 'k, String, T, str>;
 
-impl<T> StringMap<T> {
-    #[inline]
-    pub fn new() -> Self {
-        Self::default()
+impl<K: Borrow<str>, V> SortedVecMap<K, V, str> {
+    /// Returns the bounds in `self.keys` of the keys starting with `prefix`.
+    fn prefix_bounds(&self, prefix: &str) -> (usize, usize) {
+        let keys = self.keys();
+        let start = keys.partition_point(|k| k.borrow() < prefix);
+        let len = keys[start..].partition_point(|k| k.borrow().starts_with(prefix));
+        (start, start + len)
     }
 
+    /// Returns whether any key starts with `prefix`, with a single binary
+    /// search.
     #[inline]
-    pub fn keys(&self) -> &[String] {
-        &self.keys
+    pub fn contains_prefix(&self, prefix: &str) -> bool {
+        let keys = self.keys();
+        let start = keys.partition_point(|k| k.borrow() < prefix);
+        keys.get(start)
+            .is_some_and(|k| k.borrow().starts_with(prefix))
     }
 
+    /// Returns the number of keys starting with `prefix`, with two binary
+    /// searches.
     #[inline]
-    pub fn values(&self) -> &[T] {
-        &self.values
+    pub fn count_prefix(&self, prefix: &str) -> usize {
+        let (start, end) = self.prefix_bounds(prefix);
+        end - start
     }
 
-    #[inline]
-    pub fn len(&self) -> usize {
-        debug_assert_eq!(self.keys.len(), self.values.len());
-        self.keys.len()
+    /// Iterates, in order, over the entries whose key starts with `prefix`.
+    /// This costs two binary searches, plus the iteration itself.
+    ///
+    /// ```
+    /// use containers::StringMap;
+    ///
+    /// let mut map = StringMap::new();
+    /// map.insert("user:41:name".to_string(), 0);
+    /// map.insert("user:42:age".to_string(), 1);
+    /// map.insert("user:42:name".to_string(), 2);
+    /// map.insert("user:43:name".to_string(), 3);
+    ///
+    /// let keys = map.range_prefix("user:42:").map(|(k, _)| k).collect::<Vec<_>>();
+    /// assert_eq!(keys, vec!["user:42:age", "user:42:name"]);
+    /// ```
+    pub fn range_prefix(&self, prefix: &str) -> impl Iterator<Item = (&str, &V)> {
+        let (start, end) = self.prefix_bounds(prefix);
+        self.slice_items(start, end)
     }
 
-    #[inline]
-    pub fn key_idx(&self, s: &str) -> KeyIndex {
-        self.keys.binary_search_by(|string| string.as_str().cmp(s))
+    pub fn range_prefix_mut(&mut self, prefix: &str) -> impl Iterator<Item = (&str, &mut V)> {
+        let (start, end) = self.prefix_bounds(prefix);
+        self.slice_items_mut(start, end)
     }
 
+    /// Looks `key` up by its raw bytes, without checking that they are UTF-8.
+    /// This is sound because UTF-8 strings sort the same as their bytes.
     #[inline]
-    pub fn contains_key(&self, key: &str) -> bool {
-        self.key_idx(key).is_present()
+    fn key_idx_bytes(&self, key: &[u8]) -> Result<usize, usize> {
+        self.keys()
+            .binary_search_by(|k| k.borrow().as_bytes().cmp(key))
     }
 
     #[inline]
-    pub fn insert(&mut self, key: String, mut value: T) -> Option<T> {
-        match self.key_idx(&key) {
-            Ok(n) => {
-                // std::mem::swap(&mut self.keys[n], &mut key);
-                self.keys[n] = key;
-                std::mem::swap(&mut self.values[n], &mut value);
-                Some(value)
-            }
-            Err(n) => {
-                self.keys.insert(n, key);
-                self.values.insert(n, value);
-                None
-            }
-        }
-    }
-
-    #[inline]
-    pub fn remove(&mut self, key: &str) -> Option<T> {
-        self.key_idx(&key).ok().map(|n| {
-            self.keys.remove(n);
-            self.values.remove(n)
-        })
+    pub fn get_bytes(&self, key: &[u8]) -> Option<&V> {
+        self.key_idx_bytes(key).ok().map(|n| &self.values()[n])
     }
 
     #[inline]
-    pub fn get(&self, key: &str) -> Option<&T> {
-        self.key_idx(key).ok().map(|n| &self.values[n])
+    pub fn contains_key_bytes(&self, key: &[u8]) -> bool {
+        self.key_idx_bytes(key).is_ok()
     }
 
-    #[inline]
-    pub fn get_mut(&mut self, key: &str) -> Option<&mut T> {
-        self.key_idx(key).ok().map(|n| &mut self.values[n])
-    }
-
-    #[inline]
-    pub fn items(&self) -> impl Iterator<Item = (&str, &T)> {
-        debug_assert_eq!(self.keys.len(), self.values.len());
-        self.keys.iter().map(|s| s.as_str()).zip(self.values.iter())
-    }
-}
-
-impl<T> Index<&str> for StringMap<T> {
-    type Output = T;
+    /// Returns the position of the longest key that is a prefix of `query`.
+    ///
+    /// Any key that is a prefix of `query` sorts between that prefix and
+    /// `query`, so it is also a prefix of the largest key not greater than
+    /// `query`. Hence each miss shortens the query to its common prefix with
+    /// that key and searches again below it.
+    fn longest_prefix_idx(&self, mut query: &str) -> Option<usize> {
+        let keys = self.keys();
+        let mut end = keys.len();
+        loop {
+            end = keys[..end].partition_point(|k| k.borrow() <= query);
+            let key = keys[end.checked_sub(1)?].borrow();
+            if query.starts_with(key) {
+                return Some(end - 1);
+            }
 
-    fn index(&self, n: &str) -> &T {
-        self.get(n).unwrap()
+            let mut common = key
+                .bytes()
+                .zip(query.bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            while !query.is_char_boundary(common) {
+                common -= 1;
+            }
+            query = &query[..common];
+        }
     }
-}
 
-impl<T> IndexMut<&str> for StringMap<T> {
-    fn index_mut(&mut self, n: &str) -> &mut T {
-        self.get_mut(n).unwrap()
+    /// Returns the entry whose key is the longest prefix of `query`, if any.
+    ///
+    /// ```
+    /// use containers::StringMap;
+    ///
+    /// let mut map = StringMap::new();
+    /// map.insert("/api".to_string(), 0);
+    /// map.insert("/api/v1".to_string(), 1);
+    /// map.insert("/api/v1/users".to_string(), 2);
+    ///
+    /// assert_eq!(map.get_longest_prefix("/api/v1/teams/42"), Some(("/api/v1", &1)));
+    /// assert_eq!(map.get_longest_prefix("/static"), None);
+    /// ```
+    pub fn get_longest_prefix(&self, query: &str) -> Option<(&str, &V)> {
+        self.get_index(self.longest_prefix_idx(query)?)
     }
-}
 
-type KeyIndex = Result<usize, usize>;
-trait KeyIndexProps {
-    fn is_present(&self) -> bool;
-}
-impl KeyIndexProps for KeyIndex {
-    fn is_present(&self) -> bool {
-        self.is_ok()
+    pub fn get_longest_prefix_mut(&mut self, query: &str) -> Option<(&str, &mut V)> {
+        self.get_index_mut(self.longest_prefix_idx(query)?)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::EitherOrBoth;
     use crate::StringMap;
+    use std::ops::Bound;
 
     fn test_key_value(map: &mut StringMap<u32>, key: &str, value: u32) {
         assert!(map.contains_key(key));
@@ -181,4 +199,772 @@ mod tests {
             vec![("atest", &30), ("btest", &100), ("test2", &20)]
         );
     }
+
+    #[test]
+    fn mutable_iteration() {
+        let mut map = StringMap::<u32>::new();
+        map.insert("b".to_string(), 2);
+        map.insert("a".to_string(), 1);
+        map.insert("c".to_string(), 3);
+
+        for (_, value) in map.items_mut() {
+            *value += 10;
+        }
+        assert_eq!(map["a"], 11);
+        assert_eq!(map["b"], 12);
+        assert_eq!(map["c"], 13);
+
+        for (key, value) in map.iter_mut() {
+            if key == "b" {
+                *value = 0;
+            }
+        }
+        assert_eq!(map["b"], 0);
+
+        map.values_mut()[0] = 100;
+        assert_eq!(map["a"], 100);
+
+        assert_eq!(map.keys_iter().collect::<Vec<_>>(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn from_iter() {
+        let map = [("b", 2), ("c", 3), ("a", 1), ("b", 20)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect::<StringMap<u32>>();
+
+        assert_eq!(map.keys(), &vec!["a", "b", "c"]);
+        assert_eq!(map.values(), &vec![1, 20, 3]);
+    }
+
+    #[test]
+    fn from_sorted_iter() {
+        let map = StringMap::from_sorted_iter(
+            [("a", 1), ("b", 2), ("c", 3)]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v)),
+        );
+
+        assert_eq!(map.keys(), &vec!["a", "b", "c"]);
+        assert_eq!(map.values(), &vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn extend() {
+        let mut map = StringMap::<u32>::new();
+        map.extend([("d".to_string(), 4), ("b".to_string(), 2)]);
+        assert_eq!(map.keys(), &vec!["b", "d"]);
+
+        map.extend(
+            [("e", 5), ("a", 1), ("b", 20), ("c", 3), ("e", 50)]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v)),
+        );
+        assert_eq!(map.keys(), &vec!["a", "b", "c", "d", "e"]);
+        assert_eq!(map.values(), &vec![1, 20, 3, 4, 50]);
+
+        map.extend(std::iter::empty());
+        assert_eq!(map.len(), 5);
+
+        // The batch is merged into the map's own buffers.
+        let mut map = StringMap::<u32>::with_capacity(100);
+        map.extend([("a".to_string(), 1)]);
+        assert!(map.capacity() >= 100);
+        map.clear();
+        map.extend([("b".to_string(), 2)]);
+        assert!(map.capacity() >= 100);
+        map.extend([("c".to_string(), 3), ("a".to_string(), 1)]);
+        assert!(map.capacity() >= 100);
+        assert_eq!(map.keys(), &vec!["a", "b", "c"]);
+
+        let mut expected = std::collections::BTreeMap::new();
+        let mut map = StringMap::new();
+        for round in 0..10u32 {
+            let batch = (0..50u32)
+                .map(|i| ((i * 7 + round * 13) % 97).to_string())
+                .map(|k| (k, round))
+                .collect::<Vec<_>>();
+            expected.extend(batch.clone());
+            map.extend(batch);
+            assert!(map
+                .items()
+                .eq(expected.iter().map(|(k, v)| (k.as_str(), v))));
+        }
+    }
+
+    fn prefix_map() -> StringMap<u32> {
+        ["a", "ab", "abc", "abd", "b", "ba"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, k)| (k.to_string(), i as u32))
+            .collect()
+    }
+
+    #[test]
+    fn range_prefix() {
+        let mut map = prefix_map();
+        let keys = |map: &StringMap<u32>, prefix| {
+            map.range_prefix(prefix)
+                .map(|(k, _)| k.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(keys(&map, ""), map.keys());
+        assert_eq!(keys(&map, "ab"), vec!["ab", "abc", "abd"]);
+        assert_eq!(keys(&map, "abc"), vec!["abc"]);
+        assert_eq!(keys(&map, "b"), vec!["b", "ba"]);
+        assert!(keys(&map, "c").is_empty());
+        assert!(keys(&map, "aa").is_empty());
+
+        for (_, value) in map.range_prefix_mut("ab") {
+            *value += 10;
+        }
+        assert_eq!(map.values(), &vec![0, 11, 12, 13, 4, 5]);
+    }
+
+    #[test]
+    fn range() {
+        let mut map = prefix_map();
+        let keys = |map: &StringMap<u32>, range: (Bound<&str>, Bound<&str>)| {
+            map.range(range)
+                .map(|(k, _)| k.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(keys(&map, (Bound::Unbounded, Bound::Unbounded)), map.keys());
+        assert_eq!(
+            keys(&map, (Bound::Included("ab"), Bound::Excluded("b"))),
+            vec!["ab", "abc", "abd"]
+        );
+        assert_eq!(
+            keys(&map, (Bound::Excluded("ab"), Bound::Included("b"))),
+            vec!["abc", "abd", "b"]
+        );
+        assert_eq!(
+            keys(&map, (Bound::Included("aa"), Bound::Included("ab"))),
+            vec!["ab"]
+        );
+        assert!(keys(&map, (Bound::Included("c"), Bound::Unbounded)).is_empty());
+        assert!(keys(&map, (Bound::Included("b"), Bound::Excluded("a"))).is_empty());
+
+        assert_eq!(map.range("b"..).count(), 2);
+        assert_eq!(map.range(.."ab").count(), 1);
+
+        for (_, value) in map.range_mut("b"..) {
+            *value = 0;
+        }
+        assert_eq!(map.values(), &vec![0, 1, 2, 3, 0, 0]);
+    }
+
+    #[test]
+    fn retain() {
+        let mut map = prefix_map();
+        let capacity = map.capacity();
+
+        map.retain(|k, v| {
+            *v += 1;
+            k.len() != 2
+        });
+        assert_eq!(map.keys(), &vec!["a", "abc", "abd", "b"]);
+        assert_eq!(map.values(), &vec![1, 3, 4, 5]);
+        assert_eq!(capacity, map.capacity());
+
+        map.retain(|_, _| false);
+        assert!(map.is_empty());
+        assert_eq!(capacity, map.capacity());
+    }
+
+    #[test]
+    fn drain() {
+        let mut map = prefix_map();
+        let capacity = map.capacity();
+
+        let drained = map.drain().collect::<Vec<_>>();
+        assert!(map.is_empty());
+        assert_eq!(capacity, map.capacity());
+        assert_eq!(
+            drained,
+            ["a", "ab", "abc", "abd", "b", "ba"]
+                .into_iter()
+                .enumerate()
+                .map(|(i, k)| (k.to_string(), i as u32))
+                .collect::<Vec<_>>()
+        );
+
+        let mut map = prefix_map();
+        assert_eq!(map.drain().next(), Some(("a".to_string(), 0)));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn remove_matching() {
+        let mut map = prefix_map();
+        let capacity = map.capacity();
+
+        let extracted = map.remove_matching(|k, _| k.starts_with("ab"));
+        assert_eq!(
+            extracted,
+            vec![
+                ("ab".to_string(), 1),
+                ("abc".to_string(), 2),
+                ("abd".to_string(), 3)
+            ]
+        );
+        assert_eq!(map.keys(), &vec!["a", "b", "ba"]);
+        assert_eq!(map.values(), &vec![0, 4, 5]);
+        assert_eq!(capacity, map.capacity());
+
+        assert_eq!(map.remove_matching(|k, _| k == "b").len(), 1);
+        assert_eq!(map.keys(), &vec!["a", "ba"]);
+    }
+
+    #[test]
+    fn get_key_value() {
+        let map = prefix_map();
+        let (key, value) = map.get_key_value("abc").unwrap();
+        assert_eq!((key, value), ("abc", &2));
+        assert_eq!(key.as_ptr(), map.keys()[2].as_ptr());
+        assert_eq!(map.get_key_value("abe"), None);
+    }
+
+    #[test]
+    fn get_or_insert_with() {
+        let mut map = prefix_map();
+        let key_ptr = map.keys()[2].as_ptr();
+
+        *map.get_or_insert_with("abc", || unreachable!()) += 10;
+        assert_eq!(map["abc"], 12);
+        assert_eq!(key_ptr, map.keys()[2].as_ptr());
+
+        assert_eq!(*map.get_or_insert_with("abe", || 100), 100);
+        assert_eq!(map.keys(), &vec!["a", "ab", "abc", "abd", "abe", "b", "ba"]);
+        assert_eq!(map["abe"], 100);
+    }
+
+    #[test]
+    fn first_last() {
+        let mut map = prefix_map();
+        assert_eq!(map.first(), Some(("a", &0)));
+        assert_eq!(map.last(), Some(("ba", &5)));
+
+        assert_eq!(map.pop_first(), Some(("a".to_string(), 0)));
+        assert_eq!(map.pop_last(), Some(("ba".to_string(), 5)));
+        assert_eq!(map.keys(), &vec!["ab", "abc", "abd", "b"]);
+
+        map.retain(|_, _| false);
+        assert_eq!(map.first(), None);
+        assert_eq!(map.last(), None);
+        assert_eq!(map.pop_first(), None);
+        assert_eq!(map.pop_last(), None);
+    }
+
+    #[test]
+    fn capacity() {
+        let mut map = StringMap::<u8>::with_capacity(10);
+        assert!(map.is_empty());
+        assert!(map.capacity() >= 10);
+
+        for i in 0..10 {
+            map.insert(i.to_string(), i);
+        }
+        assert!(!map.is_empty());
+        assert!(map.capacity() >= 10);
+
+        map.reserve(100);
+        assert!(map.capacity() >= 110);
+
+        assert_eq!(map.remove("5"), Some(5));
+        assert!(map.capacity() >= 110);
+
+        map.shrink_to_fit();
+        assert_eq!(map.len(), 9);
+        assert!(map.capacity() >= 9);
+        assert!(map.capacity() < 110);
+    }
+
+    #[test]
+    fn clear() {
+        let mut map = prefix_map();
+        let capacity = map.capacity();
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.capacity(), capacity);
+        assert!(!map.contains_key("a"));
+
+        map.insert("a".to_string(), 1);
+        assert_eq!(map["a"], 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        use std::collections::HashMap;
+
+        let map = prefix_map();
+        let hash_map = map
+            .items()
+            .map(|(k, v)| (k.to_string(), *v))
+            .collect::<HashMap<_, _>>();
+
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(
+            serde_json::from_str::<HashMap<String, u32>>(&json).unwrap(),
+            hash_map
+        );
+
+        let json = serde_json::to_string(&hash_map).unwrap();
+        let round_trip = serde_json::from_str::<StringMap<u32>>(&json).unwrap();
+        assert_eq!(round_trip.keys(), map.keys());
+        assert_eq!(round_trip.values(), map.values());
+
+        let duplicates = r#"{"b": 1, "a": 2, "b": 3}"#;
+        let map = serde_json::from_str::<StringMap<u32>>(duplicates).unwrap();
+        assert_eq!(map.keys(), &vec!["a", "b"]);
+        assert_eq!(map.values(), &vec![2, 3]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_untrusted_size_hint() {
+        use serde::de::value::{Error, MapDeserializer};
+        use serde::Deserialize;
+
+        // Claims a trillion entries, but yields only two.
+        struct Lying<I>(I);
+        impl<I: Iterator> Iterator for Lying<I> {
+            type Item = I::Item;
+            fn next(&mut self) -> Option<I::Item> {
+                self.0.next()
+            }
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (1 << 40, Some(1 << 40))
+            }
+        }
+
+        let entries = Lying([("b", 2u64), ("a", 1)].into_iter());
+        let deserializer = MapDeserializer::<_, Error>::new(entries);
+        let map = StringMap::<u64>::deserialize(deserializer).unwrap();
+        assert_eq!(map.keys(), &vec!["a", "b"]);
+        assert_eq!(map.values(), &vec![1, 2]);
+    }
+
+    #[test]
+    fn clone() {
+        let map = prefix_map();
+        let clone = map.clone();
+        assert_eq!(clone.keys(), map.keys());
+        assert_eq!(clone.values(), map.values());
+    }
+
+    #[test]
+    fn debug() {
+        let mut map = StringMap::<u32>::new();
+        assert_eq!(format!("{map:?}"), "{}");
+
+        map.insert("b".to_string(), 2);
+        map.insert("a".to_string(), 1);
+        assert_eq!(format!("{map:?}"), r#"{"a": 1, "b": 2}"#);
+    }
+
+    #[test]
+    fn eq() {
+        let mut map = StringMap::<u32>::new();
+        map.insert("b".to_string(), 2);
+        map.insert("a".to_string(), 1);
+        map.insert("c".to_string(), 3);
+
+        let mut other = StringMap::<u32>::new();
+        other.insert("c".to_string(), 3);
+        other.insert("a".to_string(), 1);
+        other.insert("b".to_string(), 2);
+        assert_eq!(map, other);
+
+        other.insert("b".to_string(), 20);
+        assert_ne!(map, other);
+
+        other.insert("b".to_string(), 2);
+        other.insert("d".to_string(), 4);
+        assert_ne!(map, other);
+    }
+
+    #[test]
+    fn into_iter() {
+        let mut map = prefix_map();
+
+        let items = (&map).into_iter().collect::<Vec<_>>();
+        assert_eq!(items, map.items().collect::<Vec<_>>());
+        assert_eq!((&map).into_iter().size_hint(), (6, Some(6)));
+
+        for (key, value) in &mut map {
+            if key.starts_with('b') {
+                *value = 0;
+            }
+        }
+        assert_eq!(map.values(), &vec![0, 1, 2, 3, 0, 0]);
+
+        let owned = map.into_iter().collect::<Vec<_>>();
+        assert_eq!(
+            owned,
+            ["a", "ab", "abc", "abd", "b", "ba"]
+                .into_iter()
+                .zip([0, 1, 2, 3, 0, 0])
+                .map(|(k, v)| (k.to_string(), v))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn keys_values_iter() {
+        let map = prefix_map();
+
+        let keys = map.keys_iter();
+        assert_eq!(keys.len(), 6);
+        assert_eq!(
+            keys.rev().collect::<Vec<_>>(),
+            vec!["ba", "b", "abd", "abc", "ab", "a"]
+        );
+        let mut values = map.values_iter();
+        assert_eq!(values.len(), 6);
+        assert_eq!(values.next_back(), Some(&5));
+        assert_eq!(values.next(), Some(&0));
+        assert_eq!(values.len(), 4);
+    }
+
+    #[test]
+    fn named_iterators() {
+        struct Cursor<'a> {
+            items: super::Iter<'a, u32>,
+        }
+
+        let mut map = prefix_map();
+        let mut cursor = Cursor { items: map.items() };
+        assert_eq!(cursor.items.len(), 6);
+        assert_eq!(cursor.items.next_back(), Some(("ba", &5)));
+        assert_eq!(cursor.items.next(), Some(("a", &0)));
+        assert_eq!(
+            cursor
+                .items
+                .clone()
+                .rev()
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>(),
+            vec!["b", "abd", "abc", "ab"]
+        );
+        assert_eq!(cursor.items.len(), 4);
+
+        let mut items: super::IterMut<'_, u32> = map.items_mut();
+        *items.next_back().unwrap().1 = 10;
+        assert_eq!(items.len(), 5);
+        assert_eq!(map.values(), &[0, 1, 2, 3, 4, 10]);
+
+        let mut owned: super::IntoIter<u32> = map.into_iter();
+        assert_eq!(owned.next_back(), Some(("ba".to_string(), 10)));
+        assert_eq!(owned.len(), 5);
+        assert_eq!(owned.by_ref().count(), 5);
+        assert_eq!(owned.next(), None);
+    }
+
+    #[test]
+    fn longest_prefix() {
+        let mut map = ["/a", "/a/b", "/a/bc", "/a/b/c/d", "/b", "/é"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, k)| (k.to_string(), i as u32))
+            .collect::<StringMap<u32>>();
+        let prefix =
+            |map: &StringMap<u32>, query| map.get_longest_prefix(query).map(|(k, _)| k.to_string());
+
+        assert_eq!(prefix(&map, "/a").as_deref(), Some("/a"));
+        assert_eq!(prefix(&map, "/a/b").as_deref(), Some("/a/b"));
+        assert_eq!(prefix(&map, "/a/bc").as_deref(), Some("/a/bc"));
+        assert_eq!(prefix(&map, "/a/bcd").as_deref(), Some("/a/bc"));
+        assert_eq!(prefix(&map, "/a/b/").as_deref(), Some("/a/b"));
+        assert_eq!(prefix(&map, "/a/b/c").as_deref(), Some("/a/b"));
+        assert_eq!(prefix(&map, "/a/b/c/d/e").as_deref(), Some("/a/b/c/d"));
+        assert_eq!(prefix(&map, "/a/bd").as_deref(), Some("/a/b"));
+        assert_eq!(prefix(&map, "/a/x").as_deref(), Some("/a"));
+        assert_eq!(prefix(&map, "/aa").as_deref(), Some("/a"));
+        assert_eq!(prefix(&map, "/b/a").as_deref(), Some("/b"));
+        assert_eq!(prefix(&map, "/éa").as_deref(), Some("/é"));
+        assert_eq!(prefix(&map, "/ê").as_deref(), None);
+        assert_eq!(prefix(&map, "/").as_deref(), None);
+        assert_eq!(prefix(&map, "").as_deref(), None);
+        assert_eq!(prefix(&map, "/c").as_deref(), None);
+        assert_eq!(prefix(&map, "a").as_deref(), None);
+
+        *map.get_longest_prefix_mut("/a/b/x").unwrap().1 = 10;
+        assert_eq!(map["/a/b"], 10);
+        assert!(map.get_longest_prefix_mut("/c").is_none());
+
+        map.insert("".to_string(), 100);
+        assert_eq!(prefix(&map, "/c").as_deref(), Some(""));
+        assert_eq!(prefix(&map, "").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn split_off() {
+        let mut map = prefix_map();
+
+        let tail = map.split_off("abd");
+        assert_eq!(map.keys(), &vec!["a", "ab", "abc"]);
+        assert_eq!(tail.keys(), &vec!["abd", "b", "ba"]);
+        assert_eq!(tail.values(), &vec![3, 4, 5]);
+
+        let tail = map.split_off("aa");
+        assert_eq!(map.keys(), &vec!["a"]);
+        assert_eq!(tail.keys(), &vec!["ab", "abc"]);
+
+        assert!(map.split_off("b").is_empty());
+        assert_eq!(map.split_off("").keys(), &vec!["a"]);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn append() {
+        let mut map = prefix_map();
+        let mut other = ["0", "abc", "abcd", "b", "c"]
+            .into_iter()
+            .map(|k| (k.to_string(), 100))
+            .collect::<StringMap<u32>>();
+
+        map.append(&mut other);
+        assert!(other.is_empty());
+        assert_eq!(
+            map.keys(),
+            &vec!["0", "a", "ab", "abc", "abcd", "abd", "b", "ba", "c"]
+        );
+        assert_eq!(map.values(), &vec![100, 0, 1, 100, 100, 3, 100, 5, 100]);
+
+        other.insert("d".to_string(), 1);
+        map.append(&mut other);
+        assert_eq!(map.last(), Some(("d", &1)));
+
+        let mut empty = StringMap::with_capacity(20);
+        empty.append(&mut map);
+        assert!(map.is_empty());
+        assert_eq!(empty.len(), 10);
+        assert!(empty.capacity() >= 20);
+    }
+
+    #[test]
+    fn positional_access() {
+        let mut map = prefix_map();
+
+        for (i, key) in map.keys().iter().enumerate() {
+            assert_eq!(map.index_of(key), Some(i));
+            assert_eq!(map.get_index(i), Some((key.as_str(), &(i as u32))));
+        }
+        assert_eq!(map.index_of("aa"), None);
+        assert_eq!(map.get_index(6), None);
+
+        *map.get_index_mut(2).unwrap().1 = 20;
+        assert_eq!(map["abc"], 20);
+        assert!(map.get_index_mut(6).is_none());
+
+        assert_eq!(map.remove_index(1), Some(("ab".to_string(), 1)));
+        assert_eq!(map.remove_index(5), None);
+        assert_eq!(map.get_index(1), Some(("abc", &20)));
+        assert_eq!(map.index_of("abc"), Some(1));
+        assert_eq!(map.index_of("ba"), Some(4));
+
+        map.insert("0".to_string(), 0);
+        assert_eq!(map.index_of("abc"), Some(2));
+    }
+
+    #[test]
+    fn insert_keeps_stored_key() {
+        let mut map = prefix_map();
+        let key_ptr = map.keys()[2].as_ptr();
+
+        assert_eq!(map.insert("abc".to_string(), 20), Some(2));
+        assert_eq!(key_ptr, map.keys()[2].as_ptr());
+
+        let key = "abc".to_string();
+        let incoming_ptr = key.as_ptr();
+        let (key, value) = map.insert_full(key, 200);
+        assert_eq!(value, Some(20));
+        assert_eq!(key.as_ref().unwrap().as_ptr(), incoming_ptr);
+        assert_eq!(key_ptr, map.keys()[2].as_ptr());
+
+        assert_eq!(map.insert_full("abe".to_string(), 4), (None, None));
+        assert_eq!(map["abe"], 4);
+    }
+
+    #[test]
+    fn insert_ref() {
+        let mut map = prefix_map();
+        let key_ptr = map.keys()[2].as_ptr();
+
+        assert_eq!(map.insert_ref("abc", 20), Some(2));
+        assert_eq!(map["abc"], 20);
+        assert_eq!(key_ptr, map.keys()[2].as_ptr());
+
+        assert_eq!(map.insert_ref("abe", 4), None);
+        assert_eq!(map.keys(), &vec!["a", "ab", "abc", "abd", "abe", "b", "ba"]);
+        assert_eq!(map["abe"], 4);
+    }
+
+    #[test]
+    fn byte_lookups() {
+        let keys = ["a", "z", "é", "ê", "日本", "日本語", "€", "\u{10000}", "ab"];
+        let map = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k.to_string(), i))
+            .collect::<StringMap<usize>>();
+
+        let mut sorted = keys.map(|k| k.as_bytes());
+        sorted.sort();
+        assert_eq!(
+            map.keys().iter().map(|k| k.as_bytes()).collect::<Vec<_>>(),
+            sorted
+        );
+
+        for (i, key) in keys.iter().enumerate() {
+            assert!(map.contains_key_bytes(key.as_bytes()));
+            assert_eq!(map.get_bytes(key.as_bytes()), Some(&i));
+        }
+        assert!(!map.contains_key_bytes(b"b"));
+        assert!(!map.contains_key_bytes(&"日本".as_bytes()[..3]));
+        assert_eq!(map.get_bytes(&[0xff, 0xfe]), None);
+    }
+
+    #[test]
+    fn borrowed_lookups() {
+        use std::borrow::Cow;
+
+        let mut map = prefix_map();
+        let owned = "abc".to_string();
+        let cow: Cow<str> = Cow::Owned("abd".to_string());
+
+        assert_eq!(map.get(&owned), Some(&2));
+        let borrowed: &String = &owned;
+        assert_eq!(map.get(borrowed), Some(&2));
+        assert_eq!(map.get(&cow), Some(&3));
+        assert!(map.contains_key(&cow));
+        assert_eq!(map[&cow], 3);
+
+        assert_eq!(map.remove(&cow), Some(3));
+        assert_eq!(map.remove(&owned), Some(2));
+        assert!(!map.contains_key(&owned));
+    }
+
+    #[test]
+    fn contains_count_prefix() {
+        let mut map = prefix_map();
+
+        assert!(map.contains_prefix(""));
+        assert_eq!(map.count_prefix(""), map.len());
+        assert!(map.contains_prefix("a"));
+        assert_eq!(map.count_prefix("a"), 4);
+        assert!(map.contains_prefix("ab"));
+        assert_eq!(map.count_prefix("ab"), 3);
+        assert!(map.contains_prefix("abc"));
+        assert_eq!(map.count_prefix("abc"), 1);
+        assert!(!map.contains_prefix("abcd"));
+        assert_eq!(map.count_prefix("abcd"), 0);
+        assert!(!map.contains_prefix("aa"));
+        assert_eq!(map.count_prefix("aa"), 0);
+        assert!(!map.contains_prefix("c"));
+        assert_eq!(map.count_prefix("c"), 0);
+        assert!(!map.contains_prefix("0"));
+
+        map.clear();
+        assert!(!map.contains_prefix(""));
+        assert_eq!(map.count_prefix(""), 0);
+    }
+
+    #[test]
+    fn clear_recycle_keys() {
+        let mut map = prefix_map();
+        let capacity = map.capacity();
+        let key_ptrs = map.keys().iter().map(|k| k.as_ptr()).collect::<Vec<_>>();
+
+        let mut sink = vec![];
+        map.clear_recycle_keys(&mut sink);
+        assert!(map.is_empty());
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(sink, vec!["a", "ab", "abc", "abd", "b", "ba"]);
+        assert_eq!(
+            sink.iter().map(|k| k.as_ptr()).collect::<Vec<_>>(),
+            key_ptrs
+        );
+
+        for (i, mut key) in sink.drain(..).enumerate() {
+            key.clear();
+            key.push_str(&i.to_string());
+            map.insert(key, i as u32);
+        }
+        assert_eq!(map.keys(), &vec!["0", "1", "2", "3", "4", "5"]);
+    }
+
+    #[test]
+    fn merge_join() {
+        let left = prefix_map();
+        let right = ["0", "ab", "abd", "b", "c"]
+            .into_iter()
+            .map(|k| (k.to_string(), k.len()))
+            .collect::<StringMap<usize>>();
+
+        assert_eq!(
+            left.merge_join(&right).collect::<Vec<_>>(),
+            vec![
+                ("0", EitherOrBoth::Right(&1)),
+                ("a", EitherOrBoth::Left(&0)),
+                ("ab", EitherOrBoth::Both(&1, &2)),
+                ("abc", EitherOrBoth::Left(&2)),
+                ("abd", EitherOrBoth::Both(&3, &3)),
+                ("b", EitherOrBoth::Both(&4, &1)),
+                ("ba", EitherOrBoth::Left(&5)),
+                ("c", EitherOrBoth::Right(&1)),
+            ]
+        );
+        assert_eq!(
+            left.diff_keys(&right).collect::<Vec<_>>(),
+            vec!["0", "a", "abc", "ba", "c"]
+        );
+    }
+
+    #[test]
+    fn merge_join_disjoint_identical() {
+        let left = prefix_map();
+        let right = ["c", "d"]
+            .into_iter()
+            .map(|k| (k.to_string(), ()))
+            .collect::<StringMap<()>>();
+
+        let joined = left.merge_join(&right).collect::<Vec<_>>();
+        assert_eq!(joined.len(), 8);
+        assert!(joined[..6].iter().all(|(_, e)| e.left().is_some()));
+        assert!(joined[6..].iter().all(|(_, e)| e.right().is_some()));
+        assert_eq!(left.diff_keys(&right).count(), 8);
+
+        let same = left.clone();
+        assert!(left.merge_join(&same).all(|(_, e)| e.is_both()));
+        assert_eq!(left.diff_keys(&same).count(), 0);
+
+        let empty = StringMap::<u32>::new();
+        assert_eq!(empty.merge_join(&empty).count(), 0);
+        assert_eq!(left.diff_keys(&empty).count(), 6);
+    }
+
+    #[test]
+    fn hash_index() {
+        let mut map = StringMap::with_hash_index();
+        for i in 0..100 {
+            map.insert(i.to_string(), i);
+        }
+        let plain = (0..100)
+            .map(|i| (i.to_string(), i))
+            .collect::<StringMap<_>>();
+        assert_eq!(map, plain);
+        for i in 0..100 {
+            assert_eq!(map.get(&i.to_string()), Some(&i));
+            assert_eq!(map.index_of(&i.to_string()), plain.index_of(&i.to_string()));
+        }
+        assert_eq!(map.key_idx("100"), plain.key_idx("100"));
+        assert_eq!(map.key_idx("5a"), plain.key_idx("5a"));
+    }
 }
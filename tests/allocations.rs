@@ -0,0 +1,101 @@
+use containers::CompactStringMap;
+use containers::StringMap;
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::cell::Cell;
+
+/// Counts the allocations made by the current thread, so that tests running
+/// in parallel don't interfere with each other.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the number of allocations made while running `f`.
+fn allocations<R>(f: impl FnOnce() -> R) -> (usize, R) {
+    let before = ALLOCATIONS.with(|n| n.get());
+    let ret = f();
+    (ALLOCATIONS.with(|n| n.get()) - before, ret)
+}
+
+#[test]
+fn compact_string_map_insertion() {
+    const N: usize = 10_000;
+    let keys = (0..N).map(|i| i.to_string()).collect::<Vec<_>>();
+
+    let (string_map, _) = allocations(|| {
+        let mut map = StringMap::with_capacity(N);
+        for (i, key) in keys.iter().enumerate() {
+            map.insert(key.clone(), i);
+        }
+        map
+    });
+    let (compact, map) = allocations(|| {
+        let mut map = CompactStringMap::with_capacity(N, keys.iter().map(|k| k.len()).sum());
+        for (i, key) in keys.iter().enumerate() {
+            map.insert(key, i);
+        }
+        map
+    });
+
+    assert!(string_map >= N);
+    assert_eq!(compact, 3);
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(map[key], i);
+    }
+}
+
+#[test]
+fn string_map_entry_ref() {
+    let mut map = (0..100)
+        .map(|i| (i.to_string(), 0))
+        .collect::<StringMap<usize>>();
+    let keys = (0..200).map(|i| i.to_string()).collect::<Vec<_>>();
+
+    let (hits, _) = allocations(|| {
+        for _ in 0..10 {
+            for key in &keys[..100] {
+                *map.entry_ref(key.as_str()).or_insert_with(|| 0) += 1;
+                map.entry_ref(key.as_str()).and_modify(|v| *v += 1);
+            }
+        }
+    });
+    assert_eq!(hits, 0);
+    assert!(map.values().iter().all(|&v| v == 20));
+
+    // A vacant entry that is not filled does not allocate either.
+    let (untouched, _) =
+        allocations(|| map.entry_ref("missing").and_modify(|v| *v += 1).key().len());
+    assert_eq!(untouched, 0);
+
+    map.reserve(100);
+    let (misses, _) = allocations(|| {
+        for key in &keys[100..] {
+            map.entry_ref(key.as_str()).or_default();
+        }
+    });
+    assert_eq!(misses, 100);
+    assert_eq!(map.len(), 200);
+    assert_eq!(map.entry_ref("150").key(), "150");
+}